@@ -1,66 +1,129 @@
 use global_lib::*;
 use std::env;
-// use public_ip;
 
-const BUF_SIZE: usize = 5000;
-const SECRET: u128 = std::u128::MAX;
+const SECRET: u128 = u128::MAX;
+/// Runs the nodes as tasks of the interface process, connected by in-memory channels.
+const IN_PROCESS_FLAG: &str = "--in-process";
+/// Followed by a seed, runs the nodes in the process on a virtual clock, see `runtime`.
+const SIMULATE_FLAG: &str = "--simulate=";
+/// Followed by hosts separated by commas, the nodes the interface runs bind them in turn.
+const NODE_HOSTS_FLAG: &str = "--node-hosts=";
+/// The interface runs no node, it waits for nodes started on other hosts to connect.
+const EXTERNAL_FLAG: &str = "--external";
+/// The streams between the processes are mutually authenticated TLS, see `Tls`.
+const TLS_FLAG: &str = "--tls";
+
+/// Nodes to corrupt after `delay` ms, unless the round is over.
+struct Corruption {
+    round: usize,
+    nodes: Vec<u16>,
+    byz_comp: ByzComp,
+    delay: u64,
+}
+
+/// Nodes to crash after `delay` ms, unless the round is over.
+struct Crash {
+    round: usize,
+    nodes: Vec<u16>,
+    delay: u64,
+}
 
 struct Interface {
     args: Args,
     eval: Evaluation,
+    /// Address the nodes reach the interface at.
     interface_ip: String,
+    /// Hosts bound by the nodes the interface runs, their default one when empty.
+    node_hosts: Vec<String>,
+    /// Whether the nodes are started on other hosts instead of by the interface.
+    external: bool,
     nodes: Vec<String>,
-    output_count: u16,
+    /// Nodes whose output of the current round arrived.
+    answered: HashSet<u16>,
+    /// Nodes known to have crashed, crashed by the config or found by a failed send. They are
+    /// not waited for.
+    crashed: Vec<u16>,
+    /// Crashed nodes that connected again, restarted from their state directory. They take
+    /// part again from the next sharing.
+    rejoined: Vec<u16>,
     result: ResultFields,
     reconstruction_time: Duration,
     hmt: usize,
     interrupt: bool,
     timer: Instant,
+    /// Incremented at each sharing and reconstruction, late corruptions are dropped.
+    round: usize,
+    /// Whether a node ended the current round with an error.
+    failed: bool,
+    adaptive: Vec<u16>,
+    transport: Arc<dyn Transport>,
+    /// The network of the nodes run in the process, `None` when they are processes.
+    memory: Option<Arc<MemoryNetwork>>,
+    /// Notified once the last state of the config is over.
+    done: Arc<Notify>,
+    /// Notified when a node connects.
+    connected: Arc<Notify>,
 }
 
 impl Interface {
-    async fn new(_public: bool) -> (Interface, TcpListener) {
-        // let ip = if public { public_ip::addr().await.expect("Failed to get public ip").to_string() } else { "127.0.0.1".to_string() };
-        // let (port, listener) = generate_random_port(&ip).await;
-        let listener = TcpListener::bind(INTERFACE_IP)
-            .await
-            .expect("Failed to bind interface");
-        (
-            Interface {
-                args: Args::default(),
-                interface_ip: INTERFACE_IP.to_string(),
-                eval: Evaluation::default(),
-                output_count: 0,
-                nodes: Vec::new(),
-                result: ResultFields::new(),
-                reconstruction_time: 0,
-                hmt: 0,
-                interrupt: false,
-                timer: Instant::now(),
-            },
-            listener,
-        )
+    /// The interface reached at `interface_ip`, through `memory` when the nodes run in the
+    /// process.
+    fn new(interface_ip: String, memory: Option<Arc<MemoryNetwork>>) -> Interface {
+        let transport: Arc<dyn Transport> = match &memory {
+            Some(memory) => memory.clone(),
+            None => Arc::new(TcpTransport),
+        };
+        Interface {
+            args: Args::default(),
+            interface_ip,
+            node_hosts: Vec::new(),
+            external: false,
+            eval: Evaluation::default(),
+            answered: HashSet::new(),
+            crashed: Vec::new(),
+            rejoined: Vec::new(),
+            nodes: Vec::new(),
+            result: ResultFields::new(),
+            reconstruction_time: 0,
+            hmt: 0,
+            interrupt: false,
+            timer: Instant::now(),
+            round: 0,
+            failed: false,
+            adaptive: Vec::new(),
+            transport,
+            memory,
+            done: Arc::new(Notify::new()),
+            connected: Arc::new(Notify::new()),
+        }
     }
 
-    async fn new_command(interface: Wrapped<Interface>, ip: String, bytes: Bytes<'_>) -> bool {
-        match bytes[0].into() {
-            InterfaceCode::CONNECT => Self::add_node(interface, ip, &bytes[1..]).await,
-            InterfaceCode::OUTPUT => Self::new_output(interface, &bytes[1..]).await,
-            InterfaceCode::FROMFILE => Self::load_file(interface, &bytes[1..]).await,
-            InterfaceCode::INTERRUPT => Self::interrupt(interface).await,
+    /// `from` is the node that sent `msg` when its stream authenticated it.
+    async fn new_command(
+        interface: Wrapped<Interface>,
+        from: Option<String>,
+        msg: InterfaceMessage,
+    ) {
+        match msg {
+            InterfaceMessage::Connect { addr } => Self::add_node(interface, addr).await,
+            InterfaceMessage::Output {
+                round,
+                index,
+                result,
+            } => Self::new_output(interface, from, round, index, result).await,
+            InterfaceMessage::FromFile { path } => Self::load_file(interface, path).await,
+            InterfaceMessage::Interrupt {} => Self::interrupt(interface).await,
         }
-        false
     }
 
     async fn interrupt(interface: Wrapped<Interface>) {
         interface.lock().await.interrupt = true;
     }
 
-    async fn load_file(interface: Wrapped<Interface>, bytes: Bytes<'_>) {
+    async fn load_file(interface: Wrapped<Interface>, path: String) {
         {
             let mut interface = interface.lock().await;
-            let path = String::from_utf8_lossy(trim_buf(bytes)).to_string();
-            interface.args = match Args::from_file(path) {
+            interface.args = match Args::from_file(path, &nodes::protocol_names()) {
                 Ok(arg) => arg,
                 Err(e) => {
                     eprintln!("{e}");
@@ -91,12 +154,24 @@ impl Interface {
 
     async fn fix_node_number(&mut self) -> bool {
         let n = self.args.n() as usize;
-        if self.nodes.len() < n {
-            for _ in self.nodes.len()..n {
+        if self.nodes.len() < n && !self.external {
+            for i in self.nodes.len()..n {
                 let ip = self.interface_ip.clone();
+                if let Some(memory) = &self.memory {
+                    let node = nodes::spawn_in_process(memory.clone(), ip, i as u16 + 1, None);
+                    tokio::spawn(node);
+                    continue;
+                }
+                let hosts = &self.node_hosts;
+                let bind =
+                    (!hosts.is_empty()).then(|| BIND_FLAG.to_string() + &hosts[i % hosts.len()]);
+                let cert =
+                    Tls::get().map(|tls| INTERFACE_CERT_FLAG.to_string() + &to_hex(tls.cert()));
                 tokio::spawn(async move {
                     Command::new("../target/release/nodes")
                         .arg(&ip)
+                        .args(bind)
+                        .args(cert)
                         .status()
                         .await
                         .expect("Failed to create a new node");
@@ -107,114 +182,428 @@ impl Interface {
     }
 
     async fn ready_to_share(interface: Wrapped<Interface>) {
+        let connected = interface.lock().await.connected.clone();
         loop {
-            let interface = interface.lock().await;
-            if interface.nodes.len() >= interface.args.n() as usize {
+            let notified = connected.notified();
+            let ready = {
+                let interface = interface.lock().await;
+                interface.nodes.len() >= interface.args.n() as usize
+            };
+            if ready {
                 break;
             }
+            notified.await;
         }
     }
 
     async fn send_share_message(interface: Wrapped<Interface>) {
         let network_changed = interface.lock().await.fix_node_number().await;
         Self::ready_to_share(interface.clone()).await;
-        let mut interface = interface.lock().await;
-        log(format!(
-            "Sharing with: n = {}, t = {}, nb_byz = {}, hmt = {}",
-            interface.args.n(),
-            interface.args.t(),
-            interface.args.nb_byz(),
-            interface.hmt
-        ));
-        interface.output_count = 0;
-        let dealer = interface.args.nb_byz();
-        let mut msg = [0; BUF_SIZE];
-        msg[0] = CommandCode::SETUP.into();
-        LittleEndian::write_u16(&mut msg[1..], dealer);
-        msg[3] = interface.args.byz_comp().into();
-        LittleEndian::write_u16(&mut msg[4..], interface.args.t());
-        LittleEndian::write_u16(&mut msg[6..], interface.args.n());
-        let mut index = 8;
-        if network_changed {
-            for addr in &interface.nodes {
-                let bytes = addr.bytes();
-                msg[index] = bytes.len() as u8;
-                index += 1;
-                for b in bytes {
-                    msg[index] = b;
-                    index += 1;
-                }
+        let (corruption, crash, round) = {
+            let mut interface = interface.lock().await;
+            // The config stops at the first state its variations make invalid.
+            if let Err(e) = interface.args.check() {
+                log(format!("Invalid thresholds: {e}"));
+                return interface.done.notify_one();
             }
+            let rejoined = std::mem::take(&mut interface.rejoined);
+            interface.crashed.retain(|i| !rejoined.contains(i));
+            let corruption = interface.new_round(Step::Sharing);
+            let (mut byzantine, dealer) = interface.args.byzantine_and_dealer();
+            let crash = interface.crash(&byzantine, dealer);
+            let nb_static = byzantine.len() - interface.args.nb_adaptive() as usize;
+            interface.adaptive = byzantine.split_off(nb_static);
+            log(format!(
+                "Sharing with: protocol = {}, n = {}, d = {}, f = {}, byzantine = {:?}, adaptive = {:?}, crashed = {:?}, dealer = {}, hmt = {}",
+                interface.args.protocol(),
+                interface.args.n(),
+                interface.args.d(),
+                interface.args.f(),
+                byzantine,
+                interface.adaptive,
+                interface.crashed,
+                dealer,
+                interface.hmt
+            ));
+            let connect_per_message = interface.args.connect_per_message();
+            Connections::get().set_persistent(!connect_per_message);
+            let setup = Setup {
+                round: interface.round as u32,
+                dealer,
+                byz_comp: ByzComp::Honnest,
+                d: interface.args.d(),
+                f: interface.args.f(),
+                victims: interface.args.victims(),
+                decoding: interface.args.decoding(),
+                probas: interface.args.probas(),
+                connect_per_message,
+                protocol: interface.args.protocol().to_string(),
+                n: interface.args.n(),
+                network: if network_changed {
+                    interface.nodes.clone()
+                } else {
+                    Vec::new()
+                },
+                certs: match Tls::get() {
+                    Some(tls) if network_changed => {
+                        let pinned = |addr: &String| tls.pinned(addr).unwrap_or_default();
+                        interface.nodes.iter().map(pinned).collect()
+                    },
+                    _ => Vec::new(),
+                },
+                crashed: interface.crashed.clone(),
+                emulation: interface.args.network().clone(),
+                costs: interface.args.costs(),
+            };
+            let byz_comp = interface.args.byz_comp();
+            let setup = |i| {
+                let byz_comp = if byzantine.contains(&i) {
+                    byz_comp
+                } else {
+                    ByzComp::Honnest
+                };
+                NodeMessage::Setup {
+                    setup: Setup {
+                        byz_comp,
+                        ..setup.clone()
+                    },
+                }
+            };
+            let nodes = 0..interface.nodes.len() as u16;
+            interface.send_nodes(nodes, setup).await;
+            interface.contact_dealer(dealer).await;
+            (corruption, crash, interface.round)
+        };
+        Self::time_out_later(interface.clone(), round);
+        Self::crash_later(interface.clone(), crash);
+        Self::corrupt_later(interface, corruption);
+    }
+
+    async fn contact_dealer(&mut self, dealer: u16) {
+        let msg = NodeMessage::DealThis { secret: SECRET };
+        self.send_nodes([dealer], |_| msg.clone()).await;
+    }
+
+    /// Sends `msg(i)` to each node `i` of `to` that did not crash. The nodes it cannot reach
+    /// are considered crashed, the other nodes are told.
+    async fn send_nodes(
+        &mut self,
+        to: impl IntoIterator<Item = u16>,
+        msg: impl Fn(u16) -> NodeMessage,
+    ) {
+        let mut failed = self.send_each(to, msg).await;
+        while !failed.is_empty() {
+            log(format!("Crashed: {failed:?}"));
+            self.crashed.extend(&failed);
+            self.check_faults();
+            let crash = NodeMessage::Crash { nodes: failed };
+            failed = self.send_each(self.live(), |_| crash.clone()).await;
         }
-        for i in 0..interface.args.nb_byz() {
-            private_message(&interface.nodes[i as usize], &msg).await;
+    }
+
+    /// Returns the nodes that could not be reached.
+    async fn send_each(
+        &self,
+        to: impl IntoIterator<Item = u16>,
+        msg: impl Fn(u16) -> NodeMessage,
+    ) -> Vec<u16> {
+        let mut failed = Vec::new();
+        for i in to {
+            if self.crashed.contains(&i) {
+                continue;
+            }
+            let addr = &self.nodes[i as usize];
+            if let Err(e) = self.transport.send(addr, msg(i).to_frame()).await {
+                eprintln!("Failed to send to {addr}: {e}");
+                failed.push(i);
+            }
         }
-        msg[3] = ByzComp::Honnest.into();
-        for node in interface.nodes.iter().skip(interface.args.nb_byz().into()) {
-            private_message(node, &msg).await;
+        failed
+    }
+
+    /// The nodes of the current network that did not crash.
+    fn live(&self) -> Vec<u16> {
+        (0..self.args.n())
+            .filter(|i| !self.crashed.contains(i))
+            .collect()
+    }
+
+    /// Whether every node of the current network answered or crashed.
+    fn all_answered(&self) -> bool {
+        self.live().iter().all(|i| self.answered.contains(i))
+    }
+
+    /// The byzantine and the crashed nodes of the current network.
+    fn faults(&self) -> usize {
+        self.args.nb_byz() as usize + self.args.n() as usize - self.live().len()
+    }
+
+    /// The runs go on beyond the `f` faults, they are reported by `end_run`.
+    fn check_faults(&self) {
+        let crashed = self.args.n() as usize - self.live().len();
+        if self.faults() > self.args.f() as usize {
+            eprintln!(
+                "{crashed} crashed and {} byzantine nodes exceed f = {}",
+                self.args.nb_byz(),
+                self.args.f()
+            );
         }
-        interface.contact_dealer(dealer).await;
     }
 
-    async fn contact_dealer(&self, dealer: u16) {
-        let mut deal_msg = [0; 17];
-        deal_msg[0] = CommandCode::DEALTHIS.into();
-        LittleEndian::write_u128(&mut deal_msg[1..], SECRET);
-        private_message(&self.nodes[dealer as usize], &deal_msg).await;
+    /// The nodes to crash in the sharing so that `nb_crash` of the current network crashed.
+    /// They are the last honest nodes, the dealer aside.
+    fn crash(&self, byzantine: &[u16], dealer: u16) -> Option<Crash> {
+        let crashed = self.args.n() as usize - self.live().len();
+        let nodes = (0..self.args.n())
+            .rev()
+            .filter(|i| !byzantine.contains(i) && *i != dealer && !self.crashed.contains(i))
+            .take((self.args.nb_crash() as usize).saturating_sub(crashed))
+            .collect::<Vec<_>>();
+        (!nodes.is_empty()).then(|| Crash {
+            round: self.round,
+            nodes,
+            delay: self.args.crash_delay(),
+        })
     }
 
-    async fn add_node(interface: Wrapped<Interface>, ip: String, bytes: Bytes<'_>) {
-        let port = LittleEndian::read_u16(bytes);
-        let ip = extract_ip(&ip) + ":" + &port.to_string();
+    /// Every node is told, the crashed ones stop.
+    fn crash_later(interface: Wrapped<Interface>, crash: Option<Crash>) {
+        let Some(crash) = crash else {
+            return;
+        };
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(crash.delay)).await;
+            let again = {
+                let mut interface = interface.lock().await;
+                if interface.round != crash.round {
+                    return;
+                }
+                log(format!("Crashing: {:?}", crash.nodes));
+                let msg = NodeMessage::Crash {
+                    nodes: crash.nodes.clone(),
+                };
+                let live = interface.live();
+                interface.send_nodes(live, |_| msg.clone()).await;
+                for i in crash.nodes {
+                    if !interface.crashed.contains(&i) {
+                        interface.crashed.push(i);
+                    }
+                }
+                let done = interface.all_answered();
+                if done {
+                    interface.end_run(false);
+                }
+                done
+            };
+            if again {
+                Self::next_run(interface).await
+            }
+        });
+    }
+
+    /// A node connecting at a known address restarted, see `rejoined`.
+    async fn add_node(interface: Wrapped<Interface>, addr: String) {
         let mut interface = interface.lock().await;
-        //        println!("new node: {}", ip);
-        interface.nodes.push(ip);
+        //        println!("new node: {}", addr);
+        match interface.nodes.iter().position(|node| *node == addr) {
+            Some(i) => {
+                log(format!("Reconnected: {i}"));
+                if interface.crashed.contains(&(i as u16)) {
+                    interface.rejoined.push(i as u16);
+                }
+            },
+            None => interface.nodes.push(addr),
+        }
+        interface.connected.notify_waiters();
     }
 
     async fn reconstruct(interface: Wrapped<Interface>) {
-        let mut interface = interface.lock().await;
-        log(format!(
-            "Reconstructing with: n = {}, t = {}, nb_byz = {}, hmt: {}",
-            interface.args.n(),
-            interface.args.t(),
-            interface.args.nb_byz(),
-            interface.hmt
-        ));
-        interface.output_count = 0;
-        let message = [CommandCode::RECONSTRUCT.into()];
-        for addr in &interface.nodes[..interface.args.n() as usize] {
-            private_message(addr, &message).await
+        let round = {
+            let mut interface = interface.lock().await;
+            log(format!(
+                "Reconstructing with: n = {}, d = {}, f = {}, nb_byz = {}, hmt: {}",
+                interface.args.n(),
+                interface.args.d(),
+                interface.args.f(),
+                interface.args.nb_byz(),
+                interface.hmt
+            ));
+            // The nodes reveal their share as soon as they reconstruct, the adaptive ones are
+            // corrupted by the `RECONSTRUCT` itself.
+            let corruption = interface.new_round(Step::Reconstruct);
+            if let Some(corruption) = &corruption {
+                log(format!("Corrupting: {:?}", corruption.nodes));
+            }
+            let round = interface.round as u32;
+            let reconstruct = |i| {
+                let corrupt = corruption
+                    .as_ref()
+                    .filter(|c| c.nodes.contains(&i))
+                    .map(|c| c.byz_comp);
+                NodeMessage::Reconstruct { round, corrupt }
+            };
+            let live = interface.live();
+            interface.send_nodes(live, reconstruct).await;
+            interface.round
+        };
+        Self::time_out_later(interface, round);
+    }
+
+    /// Starts a new round, returns the corruption of the adaptive nodes when it happens during
+    /// `step`.
+    fn new_round(&mut self, step: Step) -> Option<Corruption> {
+        self.round += 1;
+        self.failed = false;
+        self.answered.clear();
+        if self.adaptive.is_empty() || self.args.adaptive_step() != step {
+            return None;
         }
+        Some(Corruption {
+            round: self.round,
+            nodes: self.adaptive.clone(),
+            byz_comp: self.args.byz_comp(),
+            delay: self.args.adaptive_delay(),
+        })
+    }
+
+    fn corrupt_later(interface: Wrapped<Interface>, corruption: Option<Corruption>) {
+        let Some(corruption) = corruption else {
+            return;
+        };
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(corruption.delay)).await;
+            let mut interface = interface.lock().await;
+            if interface.round != corruption.round {
+                return;
+            }
+            log(format!("Corrupting: {:?}", corruption.nodes));
+            let message = NodeMessage::Corrupt {
+                byz_comp: corruption.byz_comp,
+            };
+            interface
+                .send_nodes(corruption.nodes, |_| message.clone())
+                .await
+        });
+    }
+
+    /// Ends the current round after the `timeout` of the config, unless the nodes all answered
+    /// before. The nodes that did not answer are told to stop, the ones that died without
+    /// warning are only found then, by the failed send. The round did not time out when they
+    /// were the only ones missing.
+    fn time_out_later(interface: Wrapped<Interface>, round: usize) {
+        tokio::spawn(async move {
+            let timeout = interface.lock().await.args.timeout();
+            if timeout == 0 {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(timeout)).await;
+            {
+                let mut interface = interface.lock().await;
+                if interface.round != round {
+                    return;
+                }
+                let missing = interface
+                    .live()
+                    .into_iter()
+                    .filter(|i| !interface.answered.contains(i))
+                    .collect::<Vec<_>>();
+                let stop = NodeMessage::Stop {
+                    round: round as u32,
+                };
+                interface.send_nodes(missing, |_| stop.clone()).await;
+                let timed_out = !interface.all_answered();
+                interface.end_run(timed_out);
+            }
+            Self::next_run(interface).await
+        });
     }
 
-    async fn new_output(interface: Wrapped<Interface>, bytes: Bytes<'_>) {
+    /// The output of the node `index`, dropped when another node sent it.
+    async fn new_output(
+        interface: Wrapped<Interface>,
+        from: Option<String>,
+        round: u32,
+        index: u16,
+        result: ResultFields,
+    ) {
         let again = {
             let mut interface = interface.lock().await;
-            interface.output_count += 1;
-            let result = ResultFields::from_bytes(bytes);
+            if from.is_some_and(|from| interface.nodes.get(index as usize) != Some(&from)) {
+                return eprintln!("Output of node {index} from another node");
+            }
+            if round != interface.round as u32 || !interface.answered.insert(index) {
+                return;
+            }
+            interface.failed |= result.is_err();
+            if let Some(decoded) = result.decoded() {
+                if decoded.secret != SECRET {
+                    eprintln!(
+                        "Wrong secret decoded: {}, corrected shares from {:?}",
+                        decoded.secret, decoded.wrong
+                    );
+                }
+            }
             let reconstruct_time = result.get(TypeResultField::Reconstruction);
             interface.result += result;
-            if interface.output_count == interface.args.n() {
-                interface.finish();
-                true
-            } else {
-                if interface.is_reconstructing() && interface.output_count == interface.args.t() + 1
-                {
-                    interface.reconstruction_time = reconstruct_time;
-                    for addr in &interface.nodes[..interface.args.n() as usize] {
-                        private_message(addr, &[CommandCode::STOP.into()]).await
-                    }
-                }
-                false
+            if !interface.all_answered()
+                && interface.is_reconstructing()
+                && interface.answered.len() == interface.args.f() as usize + 1
+            {
+                interface.reconstruction_time = reconstruct_time;
+                let stop = NodeMessage::Stop {
+                    round: interface.round as u32,
+                };
+                let live = interface.live();
+                interface.send_nodes(live, |_| stop.clone()).await;
             }
+            // A node found crashed by the `STOP` is not waited for.
+            let done = interface.all_answered();
+            if done {
+                interface.end_run(false);
+            }
+            done
         };
         if again {
-            let eval: Evaluation = interface.lock().await.eval;
-            match eval {
-                Evaluation::Debit(_) => Self::process_debit(interface).await,
-                Evaluation::Latency(_) => Self::process_latency(interface).await,
-            }
+            Self::next_run(interface).await
+        }
+    }
+
+    async fn next_run(interface: Wrapped<Interface>) {
+        let eval: Evaluation = interface.lock().await.eval;
+        match eval {
+            Evaluation::Debit(_) => Self::process_debit(interface).await,
+            Evaluation::Latency(_) => Self::process_latency(interface).await,
+        }
+    }
+
+    /// Reports how the run ended and whether it had more than `f` faults, the outputs still to
+    /// come are dropped.
+    fn end_run(&mut self, timed_out: bool) {
+        self.round += 1;
+        let outcome = if timed_out {
+            "timed out"
+        } else if self.failed {
+            "ended with an error"
+        } else {
+            "finished"
+        };
+        let (faults, f) = (self.faults(), self.args.f() as usize);
+        if faults > f {
+            log(format!(
+                "Run {outcome} with {faults} faults, beyond f = {f}"
+            ));
+        } else {
+            log(format!("Run {outcome}"));
         }
+        if self.eval.is_latency() {
+            self.result
+                .set(TypeResultField::TimedOut, timed_out as Duration * 100);
+            self.result
+                .set(TypeResultField::Failed, self.failed as Duration * 100);
+            self.result
+                .set(TypeResultField::OverFaults, (faults > f) as Duration * 100);
+        }
+        self.finish();
     }
 
     fn is_reconstructing(&self) -> bool {
@@ -259,16 +648,17 @@ impl Interface {
     async fn process_latency(interface: Wrapped<Interface>) {
         let (result, evolve) = {
             let mut interface = interface.lock().await;
-            let average_verify =
-                interface.result.get(TypeResultField::Verify) / interface.args.n() as u128;
-            let average_first_receiv =
-                interface.result.get(TypeResultField::FirstReceiv) / interface.args.n() as u128;
+            // The averages are over the nodes that answered, the crashed ones did not.
+            let n = interface.answered.len().max(1) as u128;
+            let average_verify = interface.result.get(TypeResultField::Verify) / n;
+            let average_first_receiv = interface.result.get(TypeResultField::FirstReceiv) / n;
             interface
                 .result
                 .set(TypeResultField::Verify, average_verify);
             interface
                 .result
                 .set(TypeResultField::FirstReceiv, average_first_receiv);
+            interface.result.average_traffic(n);
             let evolve = interface.should_evolve();
             (interface.result.extract(), evolve)
         };
@@ -284,7 +674,10 @@ impl Interface {
             match eval {
                 Some(eval) if eval.is_debit() => Self::setup_debit(interface).await,
                 Some(_) => Self::again(interface).await,
-                None => log("Success !".to_string()),
+                None => {
+                    log("Success !".to_string());
+                    interface.lock().await.done.notify_one()
+                },
             }
         }
     }
@@ -312,6 +705,7 @@ impl Interface {
                         },
                         (None, _) => {
                             log("Success !".to_string());
+                            interface.done.notify_one();
                             return;
                         },
                     }
@@ -345,43 +739,284 @@ impl Interface {
     }
 }
 
-async fn private_message<'a>(addr: &str, msg: Bytes<'a>) {
-    TcpStream::connect(addr)
-        .await
-        .expect("Invalid node")
-        .write_all(msg)
-        .await
-        .expect("Failed to write")
-}
-
 fn handle_args(interface: Wrapped<Interface>) {
-    let path = env::args().nth(1);
+    let path = env::args().skip(1).find(|arg| !arg.starts_with("--"));
     tokio::spawn(async move {
         if let Some(path) = path {
-            Interface::load_file(interface, path.as_bytes()).await
-        }    
+            Interface::load_file(interface, path).await
+        }
     });
 }
 
-#[tokio::main]
-async fn main() {
-    let (interface, listener) = Interface::new(false).await;
+/// `cert` is the certificate of the stream of the frame when it is TLS. Such a stream pins its
+/// certificate for the address of the node it connects, the other messages are only read from
+/// the streams of the pinned certificates, and they speak for the node it is pinned for. A node
+/// that restarted connects again with the same certificate.
+fn receive(interface: &Wrapped<Interface>, cert: Option<&[u8]>, frame: Frame) {
+    match InterfaceMessage::from_frame(&frame) {
+        Ok(msg) => {
+            let mut from = None;
+            if let (Some(tls), Some(cert)) = (Tls::get(), cert) {
+                match &msg {
+                    InterfaceMessage::Connect { addr } => match tls.pinned(addr) {
+                        None => tls.pin(addr.clone(), cert.to_vec()),
+                        // The node restarted, its certificate is made from the key it kept.
+                        Some(pinned) if pinned == cert => {},
+                        Some(_) => return eprintln!("{addr} is already connected"),
+                    },
+                    _ => match tls.identify(cert) {
+                        Some(addr) => from = Some(addr),
+                        None => return eprintln!("Message from an unknown certificate"),
+                    },
+                }
+            }
+            tokio::spawn(Interface::new_command(interface.clone(), from, msg));
+        },
+        Err(e) => eprintln!("Invalid message: {e}"),
+    }
+}
+
+/// Serves the nodes run in the process until the last state is over, then writes the trace of
+/// their frames when the network is traced.
+async fn serve_in_process(interface: Wrapped<Interface>, mut inbox: Receiver<Frame>) {
+    let done = interface.lock().await.done.clone();
+    let receiving = interface.clone();
+    let serving = tokio::spawn(async move {
+        while let Some(frame) = inbox.recv().await {
+            receive(&receiving, None, frame);
+        }
+    });
+    done.notified().await;
+    serving.abort();
+    let interface = interface.lock().await;
+    if let Some(trace) = interface.memory.as_ref().and_then(|memory| memory.trace()) {
+        interface.args.write_trace(&trace)
+    }
+}
+
+fn main() {
+    let seed = arg_value(SIMULATE_FLAG).map(|seed| seed.parse::<u64>().expect("Invalid seed"));
+    runtime(seed).block_on(serve(seed))
+}
+
+async fn serve(seed: Option<u64>) {
+    let memory = match seed {
+        Some(_) => Some(MemoryNetwork::traced()),
+        None if env::args().any(|arg| arg == IN_PROCESS_FLAG) => Some(MemoryNetwork::default()),
+        None => None,
+    }
+    .map(Arc::new);
+    if let Some(memory) = memory {
+        let inbox = memory.register(INTERFACE_IP);
+        let interface = Interface::new(INTERFACE_IP.to_string(), Some(memory));
+        let interface = Arc::new(Mutex::new(interface));
+        handle_args(interface.clone());
+        return serve_in_process(interface, inbox).await;
+    }
+    if env::args().any(|arg| arg == TLS_FLAG) {
+        let tls = Tls::enable(Tls::generate(true));
+        log(format!(
+            "Certificate of the interface: {}",
+            to_hex(tls.cert())
+        ));
+    }
+    let bind = arg_value(BIND_FLAG).unwrap_or_else(|| INTERFACE_IP.to_string());
+    let listener = TcpListener::bind(&bind)
+        .await
+        .expect("Failed to bind interface");
+    let mut interface = Interface::new(arg_value(ADVERTISE_FLAG).unwrap_or(bind), None);
+    interface.node_hosts = arg_value(NODE_HOSTS_FLAG)
+        .map(|hosts| hosts.split(',').map(str::to_string).collect())
+        .unwrap_or_default();
+    interface.external = env::args().any(|arg| arg == EXTERNAL_FLAG);
     let interface = Arc::new(Mutex::new(interface));
     handle_args(interface.clone());
     loop {
-        let (mut socket, ip) = listener.accept().await.unwrap();
+        let (socket, _) = listener.accept().await.unwrap();
         let interface = interface.clone();
         tokio::spawn(async move {
-            let mut buf = [0; BUF_SIZE];
-            loop {
-                let n = match socket.read(&mut buf).await {
-                    Ok(0) | Err(_) => return,
-                    Ok(n) => n,
-                };
-                if Interface::new_command(interface.clone(), ip.to_string(), &buf[..n]).await {
-                    break;
-                }
+            let (stream, cert) = match accept_stream(socket).await {
+                Ok(accepted) => accepted,
+                Err(e) => return eprintln!("Refused a stream: {e}"),
+            };
+            let mut reader = FrameReader::new(stream);
+            while let Some(frame) = reader.next().await {
+                receive(&interface, cert.as_deref(), frame);
             }
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the `states` of a config with the nodes in the process, simulated with `seed`
+    /// when it is given, until the last one is over. Returns the results and the trace.
+    fn run_in_process(name: &str, states: &str, seed: Option<u64>) -> (String, String) {
+        run_killing(name, states, seed, None)
+    }
+
+    /// `run_in_process`, the node `index` of `killed` dies without warning after `delay` ms,
+    /// given as `(delay, index)`: its address can't be reached anymore and its task stops.
+    fn run_killing(
+        name: &str,
+        states: &str,
+        seed: Option<u64>,
+        killed: Option<(u64, u16)>,
+    ) -> (String, String) {
+        let output = format!("tmp_{name}_{}", std::process::id());
+        let path = env::temp_dir().join(format!("{output}.json"));
+        let config = format!(r#"[{{"output": "{output}"}}, {states}]"#);
+        std::fs::write(&path, config).unwrap();
+        let path = path.to_string_lossy().to_string();
+        runtime(seed).block_on(async {
+            let memory = Arc::new(match seed {
+                Some(_) => MemoryNetwork::traced(),
+                None => MemoryNetwork::default(),
+            });
+            let inbox = memory.register(INTERFACE_IP);
+            if let Some((delay, index)) = killed {
+                let memory = memory.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+                    memory.unregister(&format!("{MEMORY_HOST}:{}", index + 1));
+                });
+            }
+            let interface = Interface::new(INTERFACE_IP.to_string(), Some(memory));
+            let interface = Arc::new(Mutex::new(interface));
+            tokio::spawn(Interface::load_file(interface.clone(), path.clone()));
+            let served = serve_in_process(interface, inbox);
+            let ended = tokio::time::timeout(std::time::Duration::from_secs(300), served);
+            assert!(ended.await.is_ok(), "The runs of {name} did not end");
+        });
+        std::fs::remove_file(path).unwrap();
+        let read = |extension: &str| {
+            let path = format!("../configs/results/{output}.{extension}");
+            let content = std::fs::read_to_string(&path).unwrap_or_default();
+            let _ = std::fs::remove_file(path);
+            content
+        };
+        (read("json"), read("trace"))
+    }
+
+    #[test]
+    fn protocols_run_in_process() {
+        let (results, trace) = run_in_process(
+            "protocols_run_in_process",
+            r#"{
+                "latency": {
+                    "hmt": 2,
+                    "steps": ["total_sharing", "bytes_sent", "bytes_sent.key", "messages_received_reconstruct.protocol"]
+                },
+                "setup": {"n": [7], "t": 2, "nb_byz": 0, "byz_comp": 0}
+            },
+            {
+                "latency": {"hmt": 2, "steps": ["total_sharing", "total_reconstruct"]},
+                "setup": {"n": [7], "t": 2, "nb_byz": 2, "byz_comp": 11, "protocol": "bivariate_avss"}
+            }"#,
+            None,
+        );
+        assert!(results.contains("total_reconstruct"));
+        assert!(trace.is_empty());
+        let results = from_str::<JsonValue>(&results).unwrap();
+        let count = |field: &str| results[0]["latency"][field][0].as_u64().unwrap();
+        // The keys are only sent in the first sharing, with the protocol messages.
+        assert!(0 < count("bytes_sent.key") && count("bytes_sent.key") < count("bytes_sent"));
+        // Every node receives at least the shares revealed by d + 1 = 5 nodes.
+        assert!(count("messages_received_reconstruct.protocol") >= 5);
+    }
+
+    #[test]
+    fn simulations_only_depend_on_the_seed() {
+        let states = r#"{
+            "latency": {"hmt": 2, "steps": ["total_sharing", "total_reconstruct"]},
+            "setup": {
+                "n": [7], "t": 2, "nb_byz": 2, "byz_comp": 14, "p_honnest": 40, "p_random": 30, "p_sleeper": 30,
+                "network": {"latency": 20, "jitter": 10, "distribution": "normal"},
+                "costs": {"commit": 3000, "prove": 800, "verify": 1500, "sign": 50, "verify_sign": 120}
+            }
+        },
+        {
+            "latency": {"hmt": 2, "steps": ["total_sharing", "total_reconstruct"]},
+            "setup": {
+                "n": [7], "t": 2, "nb_byz": 2, "byz_comp": 13, "protocol": "bivariate_avss",
+                "network": {"latency": 20, "jitter": 10, "bandwidth": 500},
+                "costs": {"eval": 40, "decode": 600}
+            }
+        }"#;
+        let (results, trace) =
+            run_in_process("simulations_only_depend_on_the_seed", states, Some(3));
+        assert!(!trace.is_empty());
+        assert_eq!(
+            run_in_process("simulations_only_depend_on_the_seed", states, Some(3)),
+            (results.clone(), trace.clone())
+        );
+        let other = run_in_process("simulations_only_depend_on_the_seed", states, Some(4));
+        assert_ne!(other.1, trace);
+    }
+
+    #[test]
+    fn crashed_nodes_are_not_waited_for() {
+        let (results, _) = run_in_process(
+            "crashed_nodes_are_not_waited_for",
+            r#"{
+                "latency": {"hmt": 2, "steps": ["total_sharing", "total_reconstruct", "timed_out", "failed"]},
+                "setup": {
+                    "n": [7], "t": 2, "nb_byz": 1, "byz_comp": 1, "nb_crash": 1, "crash_delay": 30,
+                    "network": {"latency": 20}
+                }
+            },
+            {
+                "latency": {"hmt": 2, "steps": ["total_sharing", "total_reconstruct", "timed_out", "failed"]},
+                "setup": {
+                    "n": [7], "t": 2, "nb_crash": 2, "crash_delay": 10, "protocol": "bivariate_avss",
+                    "network": {"latency": 20}
+                }
+            }"#,
+            Some(5),
+        );
+        let results = from_str::<JsonValue>(&results).unwrap();
+        // The crashed nodes stay crashed, the second state crashes one more.
+        for state in [0, 1] {
+            let latency = &results[state]["latency"];
+            assert_eq!(latency["timed_out"][0].as_u64(), Some(0));
+            assert_eq!(latency["failed"][0].as_u64(), Some(0));
+            assert!(latency["total_reconstruct"][0].as_u64().is_some());
+        }
+    }
+
+    #[test]
+    fn nodes_killed_without_warning_are_found_at_the_timeout() {
+        let steps = r#""steps": ["total_sharing", "total_reconstruct", "timed_out", "failed", "over_faults"]"#;
+        let states = format!(
+            r#"{{
+                "latency": {{"hmt": 2, {steps}}},
+                "setup": {{"n": [7], "t": 2, "timeout": 2000, "network": {{"latency": 20}}}}
+            }},
+            {{
+                "latency": {{"hmt": 1, {steps}}},
+                "setup": {{
+                    "n": [7], "t": 2, "nb_byz": 2, "byz_comp": 1, "timeout": 2000,
+                    "protocol": "bivariate_avss", "network": {{"latency": 20}}
+                }}
+            }}"#
+        );
+        let (results, _) = run_killing(
+            "nodes_killed_without_warning_are_found_at_the_timeout",
+            &states,
+            Some(6),
+            Some((30, 6)),
+        );
+        let results = from_str::<JsonValue>(&results).unwrap();
+        // The other nodes share and reconstruct without the killed one, which the interface
+        // finds crashed once the sharing times out.
+        let latency = &results[0]["latency"];
+        for field in ["timed_out", "failed", "over_faults"] {
+            assert_eq!(latency[field][0].as_u64(), Some(0), "{field}");
+        }
+        assert!(latency["total_reconstruct"][0].as_u64().is_some());
+        // With the two sleepers, the killed node makes 3 faults for f = 2.
+        assert_eq!(results[1]["latency"]["over_faults"][0].as_u64(), Some(100));
+    }
+}
@@ -0,0 +1,191 @@
+use super::*;
+use ark_ff::{Field, UniformRand};
+use ark_std::Zero;
+
+/// The evaluation point `i + 1` of the node `i`, computed out of `u16` so the last index
+/// doesn't wrap to the point of the secret.
+pub(crate) fn point(i: u16) -> F {
+    F::from(u32::from(i) + 1)
+}
+
+/// `φ(x, y) = Σ coeffs[j][k] x^j y^k`, the node `i` is given the row `φ(i + 1, y)` and the
+/// column `φ(x, i + 1)` so the evaluation points match the ones of the KZG shares.
+pub struct BivariatePolynomial {
+    coeffs: Vec<Vec<F>>,
+}
+
+impl BivariatePolynomial {
+    pub fn rand(degree: usize, secret: u128) -> BivariatePolynomial {
+        let rng = &mut rng();
+        let mut coeffs = (0..=degree)
+            .map(|_| (0..=degree).map(|_| F::rand(rng)).collect::<Vec<F>>())
+            .collect::<Vec<_>>();
+        coeffs[0][0] = F::from(secret);
+        BivariatePolynomial { coeffs }
+    }
+
+    pub fn row(&self, i: u16) -> Polynomial {
+        let x = point(i);
+        let degree = self.coeffs.len();
+        let coeffs = (0..degree)
+            .map(|k| {
+                (0..degree)
+                    .rev()
+                    .fold(F::zero(), |acc, j| acc * x + self.coeffs[j][k])
+            })
+            .collect();
+        Polynomial::from_coeffs(coeffs)
+    }
+
+    pub fn column(&self, i: u16) -> Polynomial {
+        let y = point(i);
+        let coeffs = self
+            .coeffs
+            .iter()
+            .map(|row| row.iter().rev().fold(F::zero(), |acc, c| acc * y + c))
+            .collect();
+        Polynomial::from_coeffs(coeffs)
+    }
+}
+
+#[derive(Clone)]
+pub struct Polynomial {
+    pub(crate) poly: P,
+}
+
+impl Polynomial {
+    fn from_coeffs(coeffs: Vec<F>) -> Polynomial {
+        Polynomial {
+            poly: P::from_coefficients_vec(coeffs),
+        }
+    }
+
+    pub fn degree(&self) -> usize {
+        self.poly.degree()
+    }
+
+    /// Evaluation at the point of the node `i`.
+    pub fn eval(&self, i: u16) -> Share {
+        Share {
+            share: self.poly.evaluate(&point(i)),
+        }
+    }
+
+    pub fn secret(&self) -> Share {
+        Share {
+            share: self.poly.evaluate(&F::zero()),
+        }
+    }
+
+    /// Lagrange interpolation of the points `(i + 1, share)`.
+    pub fn interpolate(points: &[(u16, Share)]) -> Polynomial {
+        let mut coeffs = vec![F::zero(); points.len()];
+        for (i, (xi, yi)) in points.iter().enumerate() {
+            let xi = point(*xi);
+            let mut basis = vec![F::zero(); points.len()];
+            basis[0] = F::from(1u8);
+            let mut denom = F::from(1u8);
+            let mut len = 1;
+            for (j, (xj, _)) in points.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                let xj = point(*xj);
+                for k in (0..len).rev() {
+                    let b = basis[k];
+                    basis[k + 1] += b;
+                    basis[k] *= -xj;
+                }
+                len += 1;
+                denom *= xi - xj;
+            }
+            let factor = yi.share * denom.inverse().expect("Duplicated interpolation point");
+            coeffs
+                .iter_mut()
+                .zip(basis)
+                .for_each(|(c, b)| *c += b * factor);
+        }
+        Polynomial::from_coeffs(coeffs)
+    }
+
+    /// Returns the polynomial of degree at most `degree` going through the first `degree + 1`
+    /// points if at least `degree + t + 1` of the points agree with it. As at most `t` points
+    /// are wrong, such a polynomial is always the honest one.
+    pub fn interpolate_agreeing(
+        points: &[(u16, Share)],
+        degree: usize,
+        t: usize,
+    ) -> Option<Polynomial> {
+        if points.len() < degree + t + 1 {
+            return None;
+        }
+        let poly = Self::interpolate(&points[..degree + 1]);
+        let agreeing = points.iter().filter(|(i, s)| poly.eval(*i) == *s).count();
+        (agreeing > degree + t).then_some(poly)
+    }
+}
+
+/// Written as its coefficients.
+impl Wire for Polynomial {
+    fn write(&self, buf: &mut Vec<u8>) {
+        self.poly
+            .coeffs
+            .iter()
+            .map(|share| Share { share: *share })
+            .collect::<Vec<_>>()
+            .write(buf)
+    }
+
+    fn read(bytes: &mut Bytes<'_>) -> Result<Self, &'static str> {
+        let coeffs = Vec::<Share>::read(bytes)?;
+        Ok(Polynomial::from_coeffs(
+            coeffs.into_iter().map(|share| share.share).collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_and_columns_cross() {
+        let phi = BivariatePolynomial::rand(3, 42);
+        for i in 0..6 {
+            for j in 0..6 {
+                assert!(phi.row(i).eval(j) == phi.column(j).eval(i));
+            }
+        }
+    }
+
+    #[test]
+    fn the_last_index_is_not_the_point_of_the_secret() {
+        let phi = BivariatePolynomial::rand(2, 42);
+        assert!(phi.row(0).eval(u16::MAX) != phi.row(0).secret());
+        assert!(phi.row(u16::MAX).secret() != phi.column(0).secret());
+    }
+
+    #[test]
+    fn interpolation_recovers_secret() {
+        let phi = BivariatePolynomial::rand(3, 42);
+        let points = (0..4).map(|i| (i, phi.row(i).secret())).collect::<Vec<_>>();
+        let poly = Polynomial::interpolate(&points);
+        assert!(
+            poly.secret()
+                == Share {
+                    share: F::from(42u8)
+                }
+        );
+    }
+
+    #[test]
+    fn interpolation_rejects_a_wrong_first_point() {
+        let phi = BivariatePolynomial::rand(2, 42);
+        let mut points = (0..6).map(|i| (i, phi.row(i).secret())).collect::<Vec<_>>();
+        assert!(Polynomial::interpolate_agreeing(&points, 2, 1).is_some());
+        points[0].1 = Share {
+            share: F::from(1u8),
+        };
+        assert!(Polynomial::interpolate_agreeing(&points, 2, 1).is_none());
+    }
+}
@@ -104,7 +104,7 @@ where
         let neg_powers_of_h_time = start_timer!(|| "Generating negative powers of h in G2");
         let neg_powers_of_h = if produce_g2_powers {
             let mut neg_powers_of_beta = vec![ArkE::ScalarField::one()];
-            let mut cur = ArkE::ScalarField::one() / &beta;
+            let mut cur = ArkE::ScalarField::one() / beta;
             for _ in 0..max_degree {
                 neg_powers_of_beta.push(cur);
                 cur /= &beta;
@@ -219,7 +219,7 @@ where
             end_timer!(sample_random_poly_time);
         }
 
-        let random_ints = convert_to_bigints(&randomness.blinding_polynomial.coeffs());
+        let random_ints = convert_to_bigints(randomness.blinding_polynomial.coeffs());
         let msm_time = start_timer!(|| "MSM to compute commitment to random poly");
         let random_commitment = <ArkE::G1 as VariableBaseMSM>::msm_bigint(
             &powers.powers_of_gamma_g,
@@ -288,7 +288,7 @@ where
             let blinding_evaluation = blinding_p.evaluate(&point);
             end_timer!(blinding_eval_time);
 
-            let random_witness_coeffs = convert_to_bigints(&hiding_witness_polynomial.coeffs());
+            let random_witness_coeffs = convert_to_bigints(hiding_witness_polynomial.coeffs());
             let witness_comm_time =
                 start_timer!(|| "Computing commitment to random witness polynomial");
             w += &<ArkE::G1 as VariableBaseMSM>::msm_bigint(
@@ -343,13 +343,13 @@ where
         proof: &Proof<ArkE>,
     ) -> Result<bool, Error> {
         let check_time = start_timer!(|| "Checking evaluation");
-        let mut inner = comm.0.into_group() - &vk.g.mul(value);
+        let mut inner = comm.0.into_group() - vk.g.mul(value);
         if let Some(random_v) = proof.random_v {
             inner -= &vk.gamma_g.mul(random_v);
         }
         let lhs = ArkE::pairing(inner, vk.h);
 
-        let inner = vk.beta_h.into_group() - &vk.h.mul(point);
+        let inner = vk.beta_h.into_group() - vk.h.mul(point);
         let rhs = ArkE::pairing(proof.w, inner);
 
         end_timer!(check_time, || format!("Result: {}", lhs == rhs));
@@ -385,7 +385,7 @@ where
             let c = temp;
             g_multiplier += &(randomizer * v);
             if let Some(random_v) = proof.random_v {
-                gamma_g_multiplier += &(randomizer * &random_v);
+                gamma_g_multiplier += &(randomizer * random_v);
             }
             total_c += &c.mul(randomizer);
             total_w += &w.mul(randomizer);
@@ -450,7 +450,7 @@ where
     pub fn trim(
         pp: &UniversalParams<ArkE>,
         mut supported_degree: usize,
-    ) -> Result<(Powers<ArkE>, VerifierKey<ArkE>), Error> {
+    ) -> Result<(Powers<'_, ArkE>, VerifierKey<ArkE>), Error> {
         if supported_degree == 1 {
             supported_degree += 1;
         }
@@ -498,8 +498,8 @@ fn convert_to_bigints<F: PrimeField>(p: &[F]) -> Vec<F::BigInt> {
 #[cfg(test)]
 mod tests {
     #![allow(non_camel_case_types)]
-    use crate::kzg10::*;
-    use crate::*;
+    use super::*;
+    use ark_poly_commit::{PCCommitment, Polynomial};
 
     use ark_bls12_377::Bls12_377;
     use ark_bls12_381::Bls12_381;
@@ -538,14 +538,14 @@ mod tests {
         let mut f_comm_2 = Commitment::empty();
         f_comm_2 += (f, &comm);
 
-        assert_eq!(f_comm, f_comm_2);
+        assert_eq!(f_comm.0, f_comm_2.0);
     }
 
     fn end_to_end_test_template<ArkE, ArkP>() -> Result<(), Error>
     where
-        E: Pairing,
-        P: DenseUVPolynomial<ArkE::ScalarField, Point = ArkE::ScalarField>,
-        for<'a, 'b> &'a P: Div<&'b P, Output = P>,
+        ArkE: Pairing,
+        ArkP: DenseUVPolynomial<ArkE::ScalarField, Point = ArkE::ScalarField>,
+        for<'a, 'b> &'a ArkP: Div<&'b ArkP, Output = ArkP>,
     {
         let rng = &mut test_rng();
         for _ in 0..100 {
@@ -574,9 +574,9 @@ mod tests {
 
     fn linear_polynomial_test_template<ArkE, ArkP>() -> Result<(), Error>
     where
-        E: Pairing,
-        P: DenseUVPolynomial<ArkE::ScalarField, Point = ArkE::ScalarField>,
-        for<'a, 'b> &'a P: Div<&'b P, Output = P>,
+        ArkE: Pairing,
+        ArkP: DenseUVPolynomial<ArkE::ScalarField, Point = ArkE::ScalarField>,
+        for<'a, 'b> &'a ArkP: Div<&'b ArkP, Output = ArkP>,
     {
         let rng = &mut test_rng();
         for _ in 0..100 {
@@ -602,9 +602,9 @@ mod tests {
 
     fn batch_check_test_template<ArkE, ArkP>() -> Result<(), Error>
     where
-        E: Pairing,
-        P: DenseUVPolynomial<ArkE::ScalarField, Point = ArkE::ScalarField>,
-        for<'a, 'b> &'a P: Div<&'b P, Output = P>,
+        ArkE: Pairing,
+        ArkP: DenseUVPolynomial<ArkE::ScalarField, Point = ArkE::ScalarField>,
+        for<'a, 'b> &'a ArkP: Div<&'b ArkP, Output = ArkP>,
     {
         let rng = &mut test_rng();
         for _ in 0..10 {
@@ -614,26 +614,24 @@ mod tests {
             }
             let pp = KZG10::<ArkE, ArkP>::setup(degree, false, rng)?;
             let (ck, vk) = KZG10::<ArkE, ArkP>::trim(&pp, degree)?;
-            let mut comms = Vec::new();
             let mut values = Vec::new();
             let mut points = Vec::new();
             let mut proofs = Vec::new();
+            let p = ArkP::rand(degree, rng);
+            let hiding_bound = Some(1);
+            let (comm, rand) = KZG10::<ArkE, ArkP>::commit(&ck, &p, hiding_bound, Some(rng))?;
             for _ in 0..10 {
-                let p = ArkP::rand(degree, rng);
-                let hiding_bound = Some(1);
-                let (comm, rand) = KZG10::<ArkE, ArkP>::commit(&ck, &p, hiding_bound, Some(rng))?;
                 let point = ArkE::ScalarField::rand(rng);
                 let value = p.evaluate(&point);
                 let proof = KZG10::<ArkE, ArkP>::open(&ck, &p, point, &rand)?;
 
                 assert!(KZG10::<ArkE, ArkP>::check(&vk, &comm, point, value, &proof)?);
-                comms.push(comm);
                 values.push(value);
                 points.push(point);
                 proofs.push(proof);
             }
             assert!(KZG10::<ArkE, ArkP>::batch_check(
-                &vk, &comms, &points, &values, &proofs, rng
+                &vk, &comm, &points, &values, &proofs, rng
             )?);
         }
         Ok(())
@@ -1,6 +1,15 @@
 use crate::*;
+#[allow(
+    unexpected_cfgs,
+    clippy::type_complexity,
+    clippy::extra_unused_lifetimes,
+    clippy::suspicious_op_assign_impl
+)]
 pub mod kzg10;
 pub use kzg10::*;
+pub mod bivariate;
+pub use bivariate::*;
+pub mod reed_solomon;
 
 pub use rcgen::KeyPair;
 pub use ring::signature::{Ed25519KeyPair, KeyPair as _, Signature, UnparsedPublicKey, ED25519};
@@ -12,7 +21,7 @@ pub(crate) use ark_ec::pairing::Pairing;
 pub(crate) use ark_ff::{Fp, MontBackend};
 pub(crate) use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial};
 pub(crate) use ark_poly_commit::Polynomial as ArkPolynomial;
-pub(crate) use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress};
+pub(crate) use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 pub(crate) use ark_std::test_rng;
 
 pub const BIGINT_SIZE: usize = 4;
@@ -66,7 +75,7 @@ use ark_std::Zero;
 pub fn interpolate(shares: &HashMap<u16, (Share, Proof)>) -> i128 {
     let points: Vec<(ArkShare, ArkShare)> = shares
         .iter()
-        .map(|(i, (s, _))| (F::from(i + 1), s.share))
+        .map(|(i, (s, _))| (point(*i), s.share))
         .collect();
     let mut result = ArkShare::zero();
     for (i, &(xi, yi)) in points.iter().enumerate() {
@@ -91,20 +100,16 @@ pub struct Proof {
     proof: ArkProof,
 }
 
-impl Proof {
-    pub fn write(&self, buf: &mut [u8]) -> usize {
+impl Wire for Proof {
+    fn write(&self, buf: &mut Vec<u8>) {
         self.proof
             .serialize_compressed(buf)
-            .expect("Failed to serialize");
-        self.proof.serialized_size(Compress::Yes)
+            .expect("Failed to serialize")
     }
 
-    pub fn read(buf: &[u8]) -> (Self, usize) {
-        let res = Proof {
-            proof: ArkProof::deserialize_compressed(buf).expect("Failed to deserialize"),
-        };
-        let size = res.proof.serialized_size(Compress::Yes);
-        (res, size)
+    fn read(bytes: &mut Bytes<'_>) -> Result<Self, &'static str> {
+        let proof = ArkProof::deserialize_compressed(&mut *bytes).map_err(|_| "Invalid proof")?;
+        Ok(Proof { proof })
     }
 }
 
@@ -114,19 +119,45 @@ pub struct Share {
 }
 
 impl Share {
-    pub fn write(&self, buf: &mut [u8]) -> usize {
+    /// A well-formed share of a random field element.
+    pub fn random() -> Share {
+        Share {
+            share: F::rand(&mut rng()),
+        }
+    }
+
+    /// Another share, which doesn't verify against the commitment of this one.
+    pub fn tampered(&self) -> Share {
+        Share {
+            share: self.share + F::from(1u8),
+        }
+    }
+
+    pub fn to_u128(&self) -> u128 {
+        let mut bytes = [0u8; 32];
+        self.share
+            .serialize_uncompressed(&mut bytes[..])
+            .expect("Failed to write fr to bytes");
+        u128::from_le_bytes(bytes[0..16].try_into().expect("Failed to parse in u128"))
+    }
+}
+
+impl Wire for Share {
+    fn write(&self, buf: &mut Vec<u8>) {
         self.share
             .serialize_compressed(buf)
-            .expect("Failed to serialize");
-        self.share.serialized_size(Compress::Yes)
+            .expect("Failed to serialize")
+    }
+
+    fn read(bytes: &mut Bytes<'_>) -> Result<Self, &'static str> {
+        let share = ArkShare::deserialize_compressed(&mut *bytes).map_err(|_| "Invalid share")?;
+        Ok(Share { share })
     }
+}
 
-    pub fn read(buf: &[u8]) -> (Self, usize) {
-        let res = Share {
-            share: ArkShare::deserialize_compressed(buf).expect("Failed to deserialize"),
-        };
-        let size = res.share.serialized_size(Compress::Yes);
-        (res, size)
+impl PartialEq for Share {
+    fn eq(&self, other: &Self) -> bool {
+        self.share == other.share
     }
 }
 
@@ -138,39 +169,49 @@ pub struct Commitment {
 }
 
 impl Commitment {
-    pub fn write(&self, buf: &mut [u8]) -> usize {
+    /// The same commitment announcing the degree `degree_bound`.
+    pub fn announcing(mut self, degree_bound: u32) -> Commitment {
+        self.degree_bound = degree_bound;
+        self
+    }
+}
+
+impl Wire for Commitment {
+    fn write(&self, buf: &mut Vec<u8>) {
         self.vkey
-            .serialize_compressed(&mut buf[..])
+            .serialize_compressed(&mut *buf)
             .expect("Failed to serialize");
-        let vkey_size = self.vkey.serialized_size(Compress::Yes);
         self.comm
-            .serialize_compressed(&mut buf[vkey_size..])
+            .serialize_compressed(&mut *buf)
             .expect("Failed to serialize");
-        let comm_size = self.comm.serialized_size(Compress::Yes);
-        LittleEndian::write_u32(&mut buf[vkey_size + comm_size..], self.degree_bound);
-        comm_size + vkey_size + 4
+        self.degree_bound.write(buf)
     }
 
-    pub fn read(buf: &[u8]) -> (Commitment, usize) {
-        let vkey = VerifierKey::<E>::deserialize_compressed(buf).expect("Failed to deserialize");
-        let vkey_size = vkey.serialized_size(Compress::Yes);
-        let comm = ArkCommitment::deserialize_compressed(&buf[vkey_size..])
-            .expect("Failed to deserialize");
-        let comm_size = comm.serialized_size(Compress::Yes);
-        let degree_bound = LittleEndian::read_u32(&buf[vkey_size + comm_size..]);
-        let res = Commitment {
+    fn read(bytes: &mut Bytes<'_>) -> Result<Self, &'static str> {
+        let vkey = VerifierKey::<E>::deserialize_compressed(&mut *bytes)
+            .map_err(|_| "Invalid verifier key")?;
+        let comm =
+            ArkCommitment::deserialize_compressed(&mut *bytes).map_err(|_| "Invalid commitment")?;
+        Ok(Commitment {
             comm,
             vkey,
-            degree_bound,
-        };
-        (res, comm_size + vkey_size + 4)
+            degree_bound: u32::read(bytes)?,
+        })
     }
 }
 
+/// Checks the degree announced by the commitment. The dealer chooses it: a polynomial of a
+/// higher degree committed under the announced degree `deg` passes.
 pub fn deg_check(comm: &Commitment, deg: usize) -> bool {
     comm.degree_bound == deg as u32
 }
 
+pub fn sign(keys: &KeyPair) -> Sign {
+    let ed_key_pair = Ed25519KeyPair::from_pkcs8(keys.serialize_der().as_slice())
+        .expect("Failed to parse private key");
+    ed_key_pair.sign(SIGNATURE).as_ref().to_vec()
+}
+
 pub fn is_valid_sign(p_keys: &PublicKey, sign: &Sign) -> bool {
     let raw_public_key = &p_keys[12..];
     let public_key = UnparsedPublicKey::new(&ED25519, raw_public_key);
@@ -181,9 +222,9 @@ pub fn verify(comm: &Commitment, index: u16, share: &Share, proof: &Proof) -> bo
     KZG10::<E, P>::check(&comm.vkey, &comm.comm, index.into(), share.share, &proof.proof).unwrap()
 }
 
-pub fn batch_verify(comm: &Commitment, batchs: &Vec<Batch>) -> bool {
-    let points: &Vec<<E as Pairing>::ScalarField> = &batchs.iter().map(|(i, _, _)| (*i+1).into()).collect();
+pub fn batch_verify(comm: &Commitment, batchs: &[Batch]) -> bool {
+    let points: &Vec<<E as Pairing>::ScalarField> = &batchs.iter().map(|(i, _, _)| point(*i)).collect();
     let proofs: &Vec<ArkProof> = &batchs.iter().map(|(_, p, _)| p.proof.clone()).collect();
-    let shares: &Vec<ArkShare> = &batchs.iter().map(|(_, _, s)| s.share.clone()).collect();
+    let shares: &Vec<ArkShare> = &batchs.iter().map(|(_, _, s)| s.share).collect();
     KZG10::<E, P>::batch_check(&comm.vkey, &comm.comm, points, shares, proofs, &mut test_rng()).unwrap()
 }
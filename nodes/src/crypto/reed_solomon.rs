@@ -0,0 +1,144 @@
+use super::*;
+use ark_ff::Field;
+use ark_std::{One, Zero};
+
+impl Polynomial {
+    /// Online error correction of unverified shares. Returns the polynomial of degree at most
+    /// `degree` and the senders of the wrong shares once `degree + t + 1` of the points agree
+    /// with it, which can't happen for a wrong polynomial when at most `t` points are wrong.
+    pub fn decode(
+        points: &[(u16, Share)],
+        degree: usize,
+        t: usize,
+    ) -> Option<(Polynomial, Vec<u16>)> {
+        if !Self::decodable(points.len(), degree, t) {
+            return None;
+        }
+        let errors = ((points.len() - degree - 1) / 2).min(t);
+        let poly = Self::interpolate_agreeing(points, degree, t)
+            .or_else(|| Self::berlekamp_welch(points, degree, errors))?;
+        let wrong = points
+            .iter()
+            .filter(|(i, s)| poly.eval(*i) != *s)
+            .map(|(i, _)| *i)
+            .collect::<Vec<u16>>();
+        (points.len() - wrong.len() > degree + t).then_some((poly, wrong))
+    }
+
+    /// Whether `points` points can be enough for `decode`.
+    pub fn decodable(points: usize, degree: usize, t: usize) -> bool {
+        points > degree + t
+    }
+
+    /// Finds `E` monic of degree `errors` and `Q` of degree `degree + errors` such that
+    /// `Q(x) = y E(x)` on every point, then returns `Q / E`.
+    fn berlekamp_welch(
+        points: &[(u16, Share)],
+        degree: usize,
+        errors: usize,
+    ) -> Option<Polynomial> {
+        let unknowns = degree + 2 * errors + 1;
+        let mut system = points
+            .iter()
+            .map(|(i, s)| {
+                let x = point(*i);
+                let y = s.share;
+                let mut row = Vec::with_capacity(unknowns + 1);
+                let mut power = F::one();
+                for _ in 0..=degree + errors {
+                    row.push(power);
+                    power *= x;
+                }
+                let mut power = F::one();
+                for _ in 0..errors {
+                    row.push(-y * power);
+                    power *= x;
+                }
+                row.push(y * power);
+                row
+            })
+            .collect::<Vec<Vec<F>>>();
+        let solution = solve(&mut system, unknowns)?;
+        let q = P::from_coefficients_vec(solution[..=degree + errors].to_vec());
+        let mut e = solution[degree + errors + 1..].to_vec();
+        e.push(F::one());
+        let e = P::from_coefficients_vec(e);
+        let poly = &q / &e;
+        (poly.degree() <= degree && &poly * &e == q).then_some(Polynomial { poly })
+    }
+}
+
+/// Gaussian elimination of the augmented matrix `system`, free variables are set to zero.
+fn solve(system: &mut [Vec<F>], unknowns: usize) -> Option<Vec<F>> {
+    let mut pivots = Vec::new();
+    let mut row = 0;
+    for col in 0..unknowns {
+        let Some(pivot) = (row..system.len()).find(|r| !system[*r][col].is_zero()) else {
+            continue;
+        };
+        system.swap(row, pivot);
+        let inv = system[row][col].inverse().unwrap();
+        system[row].iter_mut().for_each(|v| *v *= inv);
+        let pivot_row = system[row].clone();
+        for (r, other) in system.iter_mut().enumerate() {
+            if r != row && !other[col].is_zero() {
+                let factor = other[col];
+                other[col..]
+                    .iter_mut()
+                    .zip(&pivot_row[col..])
+                    .for_each(|(v, p)| *v -= factor * p);
+            }
+        }
+        pivots.push(col);
+        row += 1;
+    }
+    if system[row..].iter().any(|r| !r[unknowns].is_zero()) {
+        return None;
+    }
+    let mut solution = vec![F::zero(); unknowns];
+    for (r, col) in pivots.into_iter().enumerate() {
+        solution[col] = system[r][unknowns];
+    }
+    Some(solution)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shares(n: u16, degree: usize) -> Vec<(u16, Share)> {
+        let phi = BivariatePolynomial::rand(degree, 42);
+        (0..n).map(|i| (i, phi.row(i).secret())).collect()
+    }
+
+    #[test]
+    fn decode_without_errors() {
+        let points = shares(7, 2);
+        let (poly, wrong) = Polynomial::decode(&points, 2, 2).unwrap();
+        assert_eq!(poly.secret().to_u128(), 42);
+        assert!(wrong.is_empty());
+    }
+
+    #[test]
+    fn decode_finds_wrong_shares() {
+        let mut points = shares(10, 2);
+        points[0].1 = Share {
+            share: F::from(1u8),
+        };
+        points[4].1 = Share {
+            share: F::from(2u8),
+        };
+        let (poly, wrong) = Polynomial::decode(&points, 2, 3).unwrap();
+        assert_eq!(poly.secret().to_u128(), 42);
+        assert_eq!(wrong, vec![0, 4]);
+    }
+
+    #[test]
+    fn decode_waits_for_enough_shares() {
+        let mut points = shares(5, 2);
+        points[0].1 = Share {
+            share: F::from(1u8),
+        };
+        assert!(Polynomial::decode(&points, 2, 2).is_none());
+    }
+}
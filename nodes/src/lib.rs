@@ -1,3 +1,1057 @@
 mod crypto;
-pub use global_lib::*;
+mod protocols;
+mod state;
 pub use crypto::*;
+pub use global_lib::*;
+pub use protocols::protocol_names;
+use protocols::*;
+use state::*;
+use std::any::Any;
+use std::env;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+/// Probabilities of the actions of a `ByzComp::Mixed` node, sampled for every message.
+struct ProbasMaliciousAct {
+    honnest: f32,
+    random: f32,
+    sleeper: f32,
+}
+
+impl ProbasMaliciousAct {
+    fn from_percents(percents: [u8; 3]) -> ProbasMaliciousAct {
+        ProbasMaliciousAct {
+            honnest: percents[0] as f32 / 100.0,
+            random: percents[1] as f32 / 100.0,
+            sleeper: percents[2] as f32 / 100.0,
+        }
+    }
+
+    fn sample(&self) -> (ByzComp, TypeResultField) {
+        let p = rng().gen::<f32>() * (self.honnest + self.random + self.sleeper);
+        if p < self.honnest {
+            (ByzComp::Honnest, TypeResultField::HonnestActs)
+        } else if p < self.honnest + self.random {
+            (ByzComp::Random, TypeResultField::RandomActs)
+        } else {
+            (ByzComp::Sleeper, TypeResultField::SleeperActs)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExternNode {
+    pub addr: String,
+    pub p_key: PublicKey,
+}
+
+impl ExternNode {
+    fn new(p_key: PublicKey, addr: String) -> ExternNode {
+        ExternNode { addr, p_key }
+    }
+
+    fn addr(&self) -> &str {
+        &self.addr
+    }
+}
+
+#[derive(Clone)]
+pub struct ShareSet {
+    comm: Option<Commitment>,
+    set: HashMap<u16, (Share, Proof)>,
+}
+
+impl ShareSet {
+    fn new() -> ShareSet {
+        ShareSet {
+            comm: None,
+            set: HashMap::new(),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.set.clear();
+        self.comm = None;
+    }
+
+    fn len(&self) -> u16 {
+        self.set.len() as u16
+    }
+
+    fn get(&self, i: u16) -> &(Share, Proof) {
+        self.set.get(&i).as_ref().unwrap()
+    }
+
+    fn new_share(&mut self, i: u16, share: Share, proof: Proof) {
+        self.set.insert(i, (share, proof));
+    }
+
+    fn set_comm(&mut self, comm: Commitment) {
+        // println!("in");
+        self.comm = Some(comm)
+    }
+
+    fn get_comm(&self) -> &Commitment {
+        self.comm.as_ref().unwrap()
+    }
+}
+
+/// The result of a computation run off the event loop of the node, handed back to the
+/// protocol that started it, see `Node::compute`.
+pub type Computed = Box<dyn Any + Send>;
+
+#[allow(dead_code)]
+pub struct Node {
+    /// Degree of the sharing polynomial.
+    d: u16,
+    /// Number of faults tolerated.
+    f: u16,
+    /// Number of nodes attacked when the node is a byzantine dealer.
+    victims: u16,
+    probas: ProbasMaliciousAct,
+    n: u16,
+    interface_ip: String,
+    connected_node: u16,
+    /// Address advertised to the interface, it identifies the node in the network of the
+    /// setup, whatever the host and port it binds.
+    addr: String,
+    network: Vec<ExternNode>,
+    dealer: u16,
+    byz_comp: ByzComp,
+    index: u16,
+    decoding: bool,
+    keys: Arc<KeyPair>,
+    im_setup: bool,
+    shares: ShareSet,
+    reconstruct_share_set: ShareSet,
+    /// Round of the interface at the current reconstruction, the revealed shares are tagged
+    /// with it as they can arrive before the node starts the same reconstruction or after it
+    /// moved to the next sharing.
+    reconstruction: u32,
+    /// Round of the interface at the current sharing or reconstruction, given in the output.
+    round: u32,
+    timer: Instant,
+    result: ResultFields,
+    im_done: bool,
+    step: Step,
+    transport: Arc<EmulatedTransport>,
+    /// Modeled durations of the operations, spent by the protocols.
+    costs: Costs,
+    /// Cores of the node, see `compute`.
+    cores: Arc<Semaphore>,
+    /// Nodes the interface reported crashed, their keys are not waited for.
+    crashed: Vec<u16>,
+    /// Messages received before the node was set up or knew the keys, handled once it does.
+    pending: Vec<(Option<String>, NodeMessage)>,
+    /// Frames to send, in their order, see `send`.
+    outbox: UnboundedSender<(String, Frame)>,
+    /// Where the computations post their result, see `compute`.
+    computed: UnboundedSender<Computed>,
+    /// The state directory of the node, see `keep_state`.
+    store: Option<Store>,
+    /// The `SETUP` of the current sharing, kept in the `Roster`.
+    setup: Option<Setup>,
+}
+
+impl Node {
+    /// Returns the node with the results of its computations, to be handled by its event loop.
+    fn new(
+        interface_ip: String,
+        addr: String,
+        transport: Arc<dyn Transport>,
+    ) -> (Node, UnboundedReceiver<Computed>) {
+        let transport = Arc::new(EmulatedTransport::new(transport, interface_ip.clone()));
+        let (outbox, frames) = unbounded_channel();
+        tokio::spawn(send_outbox(transport.clone(), frames));
+        let (computed, results) = unbounded_channel();
+        let node = Node {
+            d: 0,
+            f: 0,
+            victims: 0,
+            probas: ProbasMaliciousAct::from_percents([100, 0, 0]),
+            n: 0,
+            interface_ip,
+            timer: Instant::now(),
+            connected_node: 0,
+            network: Vec::new(),
+            dealer: 0,
+            index: 0,
+            byz_comp: ByzComp::Honnest,
+            decoding: false,
+            keys: Arc::new(
+                KeyPair::generate(&rcgen::PKCS_ED25519).expect("Failed to construct key"),
+            ),
+            im_setup: false,
+            im_done: false,
+            shares: ShareSet::new(),
+            reconstruct_share_set: ShareSet::new(),
+            reconstruction: 0,
+            round: 0,
+            addr,
+            result: ResultFields::new(),
+            step: Step::Sharing,
+            transport,
+            costs: Costs::default(),
+            cores: Arc::new(Semaphore::new(cores())),
+            crashed: Vec::new(),
+            pending: Vec::new(),
+            outbox,
+            computed,
+            store: None,
+            setup: None,
+        };
+        (node, results)
+    }
+
+    /// The node keeps its state in `store` from now on. When it restarts, it takes back the
+    /// key pair saved there, the rest of its state is restored by `Actor::restore`.
+    fn keep_state(&mut self, store: Store) {
+        match store.load::<Identity>(IDENTITY) {
+            Some(identity) => {
+                let key = KeyPair::from_der(&identity.key).expect("Invalid key in the state");
+                self.keys = Arc::new(key);
+            }
+            None => store.save(
+                IDENTITY,
+                &Identity {
+                    addr: self.addr.clone(),
+                    key: self.keys.serialize_der(),
+                },
+            ),
+        }
+        self.store = Some(store);
+    }
+
+    /// Saves the network of the current sharing, once the node knows its keys.
+    fn save_roster(&self) {
+        let (Some(store), Some(setup)) = (&self.store, &self.setup) else {
+            return;
+        };
+        let network = self.network.iter().map(|peer| peer.addr.clone());
+        let network = network.collect::<Vec<_>>();
+        let certs = match Tls::get() {
+            Some(tls) => network
+                .iter()
+                .map(|addr| tls.pinned(addr).unwrap_or_default())
+                .collect(),
+            None => Vec::new(),
+        };
+        let roster = Roster {
+            setup: Setup {
+                network,
+                certs,
+                ..setup.clone()
+            },
+            keys: self.network.iter().map(|peer| peer.p_key.clone()).collect(),
+        };
+        store.save(ROSTER, &roster);
+    }
+
+    /// Saves `value` under `name` in the state directory of the node, when it has one.
+    pub fn save(&self, name: &str, value: &impl Wire) {
+        if let Some(store) = &self.store {
+            store.save(name, value);
+        }
+    }
+
+    /// The value saved under `name` in the state directory of the node, see `save`.
+    pub fn load<T: Wire>(&self, name: &str) -> Option<T> {
+        self.store.as_ref().and_then(|store| store.load(name))
+    }
+
+    fn setup(&mut self, setup: Setup) {
+        self.setup = Some(setup.clone());
+        self.im_done = false;
+        self.step = Step::Sharing;
+        self.round = setup.round;
+        self.dealer = setup.dealer;
+        self.byz_comp = setup.byz_comp;
+        self.d = setup.d;
+        self.f = setup.f;
+        self.victims = setup.victims;
+        self.decoding = setup.decoding;
+        self.probas = ProbasMaliciousAct::from_percents(setup.probas);
+        self.costs = setup.costs;
+        Connections::get().set_persistent(!setup.connect_per_message);
+        self.n = setup.n;
+        if self.n > self.network.len() as u16 {
+            self.connected_node = self.network.len() as u16;
+            if let Some(tls) = Tls::get() {
+                for (addr, cert) in setup.network.iter().zip(setup.certs) {
+                    tls.pin(addr.clone(), cert);
+                }
+            }
+            for addr in setup.network.iter().skip(self.network.len()) {
+                self.network.push(ExternNode::new(vec![], addr.to_string()));
+            }
+            if self.connected_node == 0 {
+                self.set_index();
+            }
+            let key = NodeMessage::Key {
+                index: self.index,
+                key: self.keys.public_key_der(),
+            };
+            for ext_node in self.network.iter().skip(self.connected_node as usize) {
+                self.send(ext_node.addr(), &key);
+            }
+        }
+        self.transport.clear_failures();
+        self.crashed = Vec::new();
+        self.peers_crashed(setup.crashed);
+        let links = (0..self.n)
+            .filter(|i| *i != self.index)
+            .map(|i| {
+                let link = setup.emulation.link(self.index, i);
+                let cuts = setup.emulation.cuts(self.index, i);
+                (self.network[i as usize].addr.clone(), link, cuts)
+            })
+            .collect();
+        self.transport.set_links(Instant::now(), links);
+        self.shares.clear();
+        self.im_setup = true;
+    }
+
+    /// The interface reports the crash of `nodes`, they count among the `f` faults. Returns
+    /// whether the node is one of them, it then stops sending.
+    fn crashed(&mut self, nodes: Vec<u16>) -> bool {
+        if nodes.contains(&self.index) {
+            self.transport.shut_down();
+            return true;
+        }
+        self.peers_crashed(nodes);
+        false
+    }
+
+    /// The frames to the crashed peers fail at once until the next setup.
+    fn peers_crashed(&mut self, nodes: Vec<u16>) {
+        for i in nodes {
+            if let Some(peer) = self.network.get(i as usize) {
+                self.transport.mark_failed(peer.addr());
+                self.crashed.push(i);
+            }
+        }
+    }
+
+    fn set_index(&mut self) {
+        self.index = self
+            .network
+            .iter()
+            .position(|node| node.addr() == self.addr)
+            .expect("The node is not in the network") as u16;
+    }
+
+    fn get_current_network(&self) -> Vec<ExternNode> {
+        self.network[0..self.n as usize].to_vec()
+    }
+
+    /// Runs `work` off the event loop, on a core of the node once the modeled `costs` of its
+    /// operations are spent, as `(cost, times)`. Its result is posted back to the loop, which
+    /// hands it to `Protocol::computed`.
+    pub fn compute<T: Send + 'static>(
+        &self,
+        costs: &[(u32, usize)],
+        work: impl FnOnce() -> T + Send + 'static,
+    ) {
+        let (cores, computed, costs) = (self.cores.clone(), self.computed.clone(), costs.to_vec());
+        tokio::spawn(async move {
+            let _core = cores.acquire_owned().await.unwrap();
+            for (cost, times) in costs {
+                spend(cost, times).await;
+            }
+            let result = compute(work).await;
+            let _ = computed.send(Box::new(result));
+        });
+    }
+
+    /// Queues `msg` to `addr`, the frames of the node leave in their order, see `send_outbox`.
+    pub fn send(&self, addr: &str, msg: &impl Message) {
+        let _ = self.outbox.send((addr.to_string(), msg.to_frame()));
+    }
+
+    /// Sends `msg` to every node of the current network, the node included.
+    pub fn broadcast(&self, msg: &impl Message) {
+        let frame = msg.to_frame();
+        for peer in &self.network[0..self.n as usize] {
+            let _ = self.outbox.send((peer.addr.clone(), frame.clone()));
+        }
+    }
+
+    /// Whether the node knows the keys of the network, but the ones of the crashed nodes.
+    fn knows_the_keys(&self) -> bool {
+        let known = |(i, peer): (usize, &ExternNode)| {
+            !peer.p_key.is_empty() || self.crashed.contains(&(i as u16))
+        };
+        self.network.iter().enumerate().all(known)
+    }
+
+    /// Whether the peer at `from`, authenticated by the certificate of its stream, may send
+    /// `msg`: the interface sends the commands, a node of the network its key and the messages
+    /// of the protocols.
+    fn authorized(&self, from: &str, msg: &NodeMessage) -> bool {
+        match msg {
+            NodeMessage::Key { index, .. } => self
+                .network
+                .get(*index as usize)
+                .is_some_and(|peer| peer.addr == from),
+            NodeMessage::Protocol { .. } => self.network.iter().any(|peer| peer.addr == from),
+            _ => from == self.interface_ip,
+        }
+    }
+
+    /// The index of the node of the network at `from`, which is `authorized`.
+    fn sender(&self, from: &str) -> u16 {
+        self.network
+            .iter()
+            .position(|peer| peer.addr == from)
+            .expect("The sender is in the network") as u16
+    }
+
+    /// Whether the node is set up and knows the keys of the network, the messages of the
+    /// protocol wait for it.
+    fn im_ready(&self) -> bool {
+        self.im_setup && self.knows_the_keys()
+    }
+
+    fn save_share(&mut self, i: u16, share: Share, proof: Proof) {
+        self.get_current_set_mut().new_share(i, share, proof);
+    }
+
+    /// Switches to the reconstruction `round`, corrupted beforehand when `corrupt` is given so
+    /// that the revealed share already follows it.
+    fn start_reconstruct(&mut self, round: u32, corrupt: Option<ByzComp>) {
+        if let Some(byz_comp) = corrupt {
+            self.byz_comp = byz_comp;
+        }
+        self.step = Step::Reconstruct;
+        self.reconstruction = round;
+        self.round = round;
+        self.reconstruct_share_set = self.shares.clone();
+        self.im_done = false;
+        self.timer = Instant::now();
+    }
+
+    /// Whether the node is still in the reconstruction `round`, checked again once a share
+    /// revealed for it is verified.
+    pub fn reconstructing(&self, round: u32) -> bool {
+        self.step == Step::Reconstruct && self.reconstruction == round
+    }
+
+    /// Ends the reconstruction `round`, the node may already be in a later one.
+    pub fn stop_reconstruct(&mut self, round: u32) {
+        if self.step != Step::Reconstruct || self.round != round {
+            return;
+        }
+        self.output(Step::Reconstruct, ErrorCode::OK);
+    }
+
+    pub fn output(&mut self, step: Step, code: ErrorCode) {
+        if self.im_done {
+            return;
+        }
+        if self.im_dealer() && step == Step::Sharing {
+            self.result
+                .set(TypeResultField::Total, self.timer.elapsed().as_millis());
+        } else if step == Step::Reconstruct {
+            self.result.set(
+                TypeResultField::Reconstruction,
+                self.timer.elapsed().as_millis(),
+            );
+        }
+        self.im_done = true;
+        if step == Step::Sharing && code == ErrorCode::OK {
+            self.save(SHARES, &(self.round, self.shares.clone()));
+        }
+        let mut result = self.result.extract();
+        result.set_code(code);
+        // The traffic since the previous output, the late frames of the previous step included.
+        result.set_traffic(step, &self.transport.take_traffic());
+        let output = InterfaceMessage::Output {
+            round: self.round,
+            index: self.index,
+            result,
+        };
+        self.send(&self.interface_ip, &output);
+    }
+
+    /// The behaviour of the node for its next message, sampled and counted in the results when
+    /// it is `ByzComp::Mixed`.
+    pub fn act(&mut self) -> ByzComp {
+        if self.byz_comp != ByzComp::Mixed {
+            return self.byz_comp;
+        }
+        let (act, field) = self.probas.sample();
+        self.result.incr(field);
+        act
+    }
+
+    /// Called when the secret was decoded from unverified shares, reported in the output.
+    pub fn decoded(&mut self, secret: &Share, wrong: &[u16]) {
+        self.result.set_decoded(secret.to_u128(), wrong.to_vec());
+    }
+
+    pub fn my_share(&self) -> &Share {
+        &self.get_current_set().get(self.index).0
+    }
+
+    pub fn my_proof(&self) -> &Proof {
+        &self.get_current_set().get(self.index).1
+    }
+
+    pub fn get_current_set(&self) -> &ShareSet {
+        match self.step {
+            Step::Sharing => &self.shares,
+            Step::Reconstruct => &self.reconstruct_share_set,
+        }
+    }
+
+    pub fn get_current_set_mut(&mut self) -> &mut ShareSet {
+        match self.step {
+            Step::Sharing => &mut self.shares,
+            Step::Reconstruct => &mut self.reconstruct_share_set,
+        }
+    }
+
+    pub fn im_dealer(&self) -> bool {
+        self.index == self.dealer
+    }
+}
+
+/// The node with the protocol it runs, owned by its event loop, see `run`.
+struct Actor {
+    node: Node,
+    protocol: Box<dyn Protocol>,
+}
+
+impl Actor {
+    fn new(node: Node) -> Actor {
+        Actor {
+            node,
+            protocol: (PROTOCOLS[0].1)(),
+        }
+    }
+
+    /// `from` is the authenticated sender of `msg`, see `Node::authorized`. Returns false once
+    /// the node crashed, see `NodeMessage::Crash`.
+    fn new_command(&mut self, from: Option<&str>, msg: NodeMessage) -> bool {
+        let node = &mut self.node;
+        match msg {
+            NodeMessage::Setup { setup } => self.setup(setup),
+            NodeMessage::Crash { nodes } => {
+                if node.crashed(nodes) {
+                    return false;
+                }
+                self.replay()
+            }
+            NodeMessage::Key { index, key } if (index as usize) < node.network.len() => {
+                node.network[index as usize].p_key = key;
+                if node.im_ready() {
+                    node.save_roster();
+                    self.replay()
+                }
+            }
+            NodeMessage::Corrupt { byz_comp } => node.byz_comp = byz_comp,
+            NodeMessage::Reconstruct { round, corrupt } => {
+                node.start_reconstruct(round, corrupt);
+                self.protocol.reconstruct(node)
+            }
+            NodeMessage::Stop { round } => node.stop_reconstruct(round),
+            // The keys of the nodes joining at the next setup wait for it too.
+            msg @ NodeMessage::Key { .. } => node.pending.push((from.map(str::to_string), msg)),
+            msg if !node.im_ready() => node.pending.push((from.map(str::to_string), msg)),
+            NodeMessage::DealThis { secret } => self.protocol.deal(node, secret),
+            NodeMessage::Protocol { protocol, bytes } => {
+                let from = from.map(|from| node.sender(from));
+                if Some(protocol) == protocol_id(self.protocol.name()) {
+                    self.protocol.new_message(node, from, &bytes)
+                }
+            }
+        }
+        true
+    }
+
+    /// A `SETUP` naming a protocol the node does not run is dropped.
+    fn setup(&mut self, setup: Setup) {
+        if self.protocol.name() != setup.protocol {
+            match protocol_from_name(&setup.protocol) {
+                Some(protocol) => self.protocol = protocol,
+                None => return eprintln!("Unknown protocol: {}", setup.protocol),
+            }
+        }
+        self.node.setup(setup);
+        self.protocol.setup(&mut self.node);
+        if self.node.im_ready() {
+            self.node.save_roster();
+        }
+        self.replay()
+    }
+
+    /// Takes back the state the node saved before it restarted: the network of its last
+    /// sharing with the keys of the nodes and its shares, so that it takes part in the
+    /// reconstruction. It sends its key again, the other nodes already have it.
+    fn restore(&mut self) {
+        let Some(roster) = self.node.load::<Roster>(ROSTER) else {
+            return;
+        };
+        let round = roster.setup.round;
+        self.setup(roster.setup);
+        let node = &mut self.node;
+        for (peer, key) in node.network.iter_mut().zip(roster.keys) {
+            if !key.is_empty() {
+                peer.p_key = key;
+            }
+        }
+        // The shares of an earlier sharing, when the node restarted before it output.
+        match node.load::<(u32, ShareSet)>(SHARES) {
+            Some((sharing, shares)) if sharing == round => node.shares = shares,
+            _ => {}
+        }
+        self.protocol.restore(node);
+        self.replay()
+    }
+
+    /// Handles the pending messages again, once the node changed.
+    fn replay(&mut self) {
+        for (from, msg) in std::mem::take(&mut self.node.pending) {
+            self.new_command(from.as_deref(), msg);
+        }
+    }
+}
+
+fn connect(node: &Node) {
+    let msg = InterfaceMessage::Connect {
+        addr: node.addr.clone(),
+    };
+    node.send(&node.interface_ip, &msg)
+}
+
+/// Sends the frames queued by the node in their order, off its event loop. With a connection
+/// per message, each frame is sent by a task of its own as its stream does not keep the order.
+async fn send_outbox(
+    transport: Arc<EmulatedTransport>,
+    mut outbox: UnboundedReceiver<(String, Frame)>,
+) {
+    while let Some((addr, frame)) = outbox.recv().await {
+        if Connections::get().is_persistent() {
+            send_frame(&*transport, &addr, frame).await;
+            continue;
+        }
+        let transport = transport.clone();
+        tokio::spawn(async move { send_frame(&*transport, &addr, frame).await });
+    }
+}
+
+/// Failures are only reported, once per crashed peer, the message is lost.
+async fn send_frame(transport: &dyn Transport, addr: &str, frame: Frame) {
+    match transport.send(addr, frame).await {
+        Err(e) if e.kind() == std::io::ErrorKind::ConnectionAborted => {}
+        Err(e) => eprintln!("Failed to send to {addr}: {e}"),
+        Ok(()) => {}
+    }
+}
+
+/// A frame with the address of its sender when the certificate of its stream authenticated
+/// it, see `Tls::identify`.
+type Received = (Option<String>, Frame);
+
+/// The event loop of the node, it owns the node and the protocol it runs. The frames are
+/// handled in their order of arrival in `inbox`, whatever the stream they came from, and the
+/// results of the computations as soon as they are `computed`. Returns when the node crashes,
+/// see `NodeMessage::Crash`.
+async fn run(node: Node, mut computed: UnboundedReceiver<Computed>, mut inbox: Receiver<Received>) {
+    let mut actor = Actor::new(node);
+    actor.restore();
+    loop {
+        tokio::select! {
+            biased;
+            Some(result) = computed.recv() => actor.protocol.computed(&mut actor.node, result),
+            received = inbox.recv() => {
+                let Some((from, frame)) = received else {
+                    return;
+                };
+                actor.node.transport.received(&frame);
+                match NodeMessage::from_frame(&frame) {
+                    Ok(msg) if from.as_ref().is_some_and(|from| !actor.node.authorized(from, &msg)) => {
+                        eprintln!("Message claiming another identity than {}", from.unwrap())
+                    }
+                    Ok(msg) => {
+                        if !actor.new_command(from.as_deref(), msg) {
+                            return;
+                        }
+                    }
+                    Err(e) => eprintln!("Invalid message: {e}"),
+                }
+            }
+        }
+    }
+}
+
+/// Feeds the frames of the streams accepted by `listener` into `inbox`, with the peer their
+/// stream authenticated when they are TLS.
+async fn listen_with(listener: TcpListener, inbox: Sender<Received>) {
+    loop {
+        let (socket, _) = listener.accept().await.unwrap();
+        let inbox = inbox.clone();
+        tokio::spawn(async move {
+            let (stream, from) = match accept_stream(socket).await {
+                Ok((stream, cert)) => {
+                    let tls = Tls::get();
+                    let from = cert.and_then(|cert| tls.and_then(|tls| tls.identify(&cert)));
+                    (stream, from)
+                }
+                Err(e) => return eprintln!("Refused a stream: {e}"),
+            };
+            let mut reader = FrameReader::new(stream);
+            while let Some(frame) = reader.next().await {
+                if inbox.send((from.clone(), frame)).await.is_err() {
+                    return;
+                }
+            }
+        });
+    }
+}
+
+/// Runs the node of the process, the interface is given as the first argument. The node binds
+/// the host given with `BIND_FLAG` and advertises the one given with `ADVERTISE_FLAG`. Its
+/// streams are TLS when the certificate of the interface is given with `INTERFACE_CERT_FLAG`.
+/// With `STATE_FLAG`, a node restarting takes back the address and the state it had.
+pub async fn begin() {
+    let interface_ip = env::args().nth(1).unwrap();
+    let bind = arg_value(BIND_FLAG).unwrap_or_else(|| "127.0.0.1".to_string());
+    let advertise = arg_value(ADVERTISE_FLAG).unwrap_or_else(|| bind.clone());
+    if advertise == "0.0.0.0" {
+        panic!("A node bound on every interface needs the address it advertises");
+    }
+    let store = arg_value(STATE_FLAG).map(Store::open);
+    let identity = store
+        .as_ref()
+        .and_then(|store| store.load::<Identity>(IDENTITY));
+    let (addr, listener) = match identity {
+        Some(identity) => {
+            let port = identity.addr.rsplit(':').next().unwrap_or_default();
+            let listener = TcpListener::bind(format!("{bind}:{port}"))
+                .await
+                .expect("Failed to bind the port of the node");
+            (identity.addr, listener)
+        }
+        None => {
+            let (port, listener) = generate_random_port(&bind).await;
+            (format!("{advertise}:{port}"), listener)
+        }
+    };
+    let (mut node, computed) = Node::new(interface_ip.clone(), addr, Arc::new(TcpTransport));
+    if let Some(store) = store {
+        node.keep_state(store);
+    }
+    if let Some(cert) = arg_value(INTERFACE_CERT_FLAG) {
+        let tls = Tls::enable(Tls::new(&node.keys.serialize_der(), false));
+        tls.pin(interface_ip, from_hex(&cert).expect("Invalid certificate"));
+    }
+    connect(&node);
+    let (inbox, frames) = channel(INBOX_SIZE);
+    tokio::spawn(listen_with(listener, inbox));
+    run(node, computed, frames).await;
+    exit(1);
+}
+
+/// Runs a node as tasks of the process, reached through `network` at `memory:<port>`. It keeps
+/// its state in `state` when it is given, see `STATE_FLAG`.
+pub async fn spawn_in_process(
+    network: Arc<MemoryNetwork>,
+    interface_ip: String,
+    port: u16,
+    state: Option<std::path::PathBuf>,
+) {
+    let addr = format!("{MEMORY_HOST}:{port}");
+    let mut frames = network.register(&addr);
+    let (mut node, computed) = Node::new(interface_ip, addr.clone(), network.clone());
+    if let Some(state) = state {
+        node.keep_state(Store::open(state));
+    }
+    connect(&node);
+    // The tasks of the process are not authenticated, their frames have no sender.
+    let (inbox, received) = channel(INBOX_SIZE);
+    tokio::spawn(async move {
+        while let Some(frame) = frames.recv().await {
+            if inbox.send((None, frame)).await.is_err() {
+                return;
+            }
+        }
+    });
+    tokio::spawn(async move {
+        run(node, computed, received).await;
+        network.unregister(&addr);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACTS: [(ByzComp, TypeResultField); 3] = [
+        (ByzComp::Honnest, TypeResultField::HonnestActs),
+        (ByzComp::Random, TypeResultField::RandomActs),
+        (ByzComp::Sleeper, TypeResultField::SleeperActs),
+    ];
+
+    fn test_node(addr: &str) -> (Node, UnboundedReceiver<Computed>) {
+        Node::new(
+            String::new(),
+            addr.to_string(),
+            Arc::new(MemoryNetwork::default()),
+        )
+    }
+
+    #[test]
+    fn sample_follows_the_percents() {
+        for (percents, (act, field)) in [[100, 0, 0], [0, 100, 0], [0, 0, 100]]
+            .into_iter()
+            .zip(ACTS)
+        {
+            let probas = ProbasMaliciousAct::from_percents(percents);
+            assert!((0..1000).all(|_| probas.sample() == (act, field)));
+        }
+        let probas = ProbasMaliciousAct::from_percents([50, 30, 20]);
+        let mut counts = ResultFields::new();
+        for _ in 0..10000 {
+            counts.incr(probas.sample().1);
+        }
+        for ((_, field), expected) in ACTS.into_iter().zip([5000, 3000, 2000]) {
+            assert!(counts.get(field).abs_diff(expected) < 500);
+        }
+    }
+
+    #[tokio::test]
+    async fn mixed_acts_are_counted() {
+        let (mut node, _) = test_node("");
+        node.byz_comp = ByzComp::Mixed;
+        node.probas = ProbasMaliciousAct::from_percents([50, 30, 20]);
+        let acts = (0..1000).map(|_| node.act()).collect::<Vec<_>>();
+        let result = node.result.extract();
+        for (act, field) in ACTS {
+            let count = acts.iter().filter(|a| **a == act).count();
+            assert_eq!(result.get(field), count as Duration);
+        }
+        node.byz_comp = ByzComp::WrongShares;
+        assert_eq!(node.act(), ByzComp::WrongShares);
+        assert!(ACTS.iter().all(|(_, field)| node.result.get(*field) == 0));
+    }
+
+    #[tokio::test]
+    async fn stored_shares_survive_a_corruption() {
+        let (mut node, _) = test_node("");
+        node.n = 7;
+        let (comm, output) = compute_proof_and_shares(node.n, 4, 42);
+        let (proof, share) = output[0].clone();
+        node.shares.set_comm(comm.clone());
+        node.shares.new_share(0, share.clone(), proof.clone());
+        node.start_reconstruct(1, Some(ByzComp::WrongShares));
+        let (i, revealed) = revealed_share(node.act(), node.index, node.n, node.my_share());
+        assert!(!verify(&comm, i + 1, &revealed, &proof));
+        for set in [&node.shares, &node.reconstruct_share_set] {
+            assert!(set.get(0).0 == share);
+            assert!(verify(set.get_comm(), 1, &set.get(0).0, &set.get(0).1));
+        }
+    }
+
+    #[tokio::test]
+    async fn nodes_on_several_hosts_share_a_port() {
+        let (mut node, _) = test_node("127.0.0.3:5000");
+        node.network = ["127.0.0.2:5000", "127.0.0.3:5000", "127.0.0.3:5001"]
+            .into_iter()
+            .map(|addr| ExternNode::new(vec![], addr.to_string()))
+            .collect();
+        node.set_index();
+        assert_eq!(node.index, 1);
+    }
+
+    fn setup(n: u16) -> NodeMessage {
+        NodeMessage::Setup {
+            setup: Setup {
+                round: 1,
+                dealer: 0,
+                byz_comp: ByzComp::Honnest,
+                d: 2,
+                f: 0,
+                victims: 0,
+                decoding: false,
+                probas: [100, 0, 0],
+                connect_per_message: false,
+                protocol: PROTOCOLS[0].0.to_string(),
+                n,
+                network: (0..n).map(|i| format!("{MEMORY_HOST}:{i}")).collect(),
+                certs: Vec::new(),
+                crashed: Vec::new(),
+                emulation: NetworkEmulation::default(),
+                costs: Costs::default(),
+            },
+        }
+    }
+
+    fn key(index: u16) -> NodeMessage {
+        NodeMessage::Key {
+            index,
+            key: vec![7],
+        }
+    }
+
+    #[tokio::test]
+    async fn messages_wait_for_the_setup_and_the_keys() {
+        let (node, mut computed) = test_node(&format!("{MEMORY_HOST}:0"));
+        let mut actor = Actor::new(node);
+        for msg in [key(1), NodeMessage::DealThis { secret: 42 }] {
+            assert!(actor.new_command(None, msg));
+        }
+        assert_eq!(actor.node.pending.len(), 2);
+        actor.new_command(None, setup(3));
+        assert_eq!(actor.node.network[1].p_key, vec![7]);
+        assert_eq!(
+            actor.node.pending,
+            vec![(None, NodeMessage::DealThis { secret: 42 })]
+        );
+        actor.new_command(None, NodeMessage::Crash { nodes: vec![2] });
+        assert_eq!(actor.node.pending.len(), 1);
+        // The last key makes the node ready, the dealing starts.
+        actor.new_command(None, key(0));
+        assert!(actor.node.pending.is_empty());
+        assert!(computed.recv().await.is_some());
+    }
+
+    #[tokio::test]
+    async fn frames_claiming_another_identity_are_refused() {
+        let (mut node, _) = test_node("node:0");
+        node.interface_ip = "interface".to_string();
+        node.network = ["node:0", "node:1"]
+            .into_iter()
+            .map(|addr| ExternNode::new(vec![], addr.to_string()))
+            .collect();
+        let protocol = NodeMessage::Protocol {
+            protocol: 0,
+            bytes: vec![0],
+        };
+        assert!(node.authorized("node:1", &key(1)));
+        assert!(!node.authorized("node:1", &key(0)));
+        assert!(!node.authorized("node:1", &key(2)));
+        assert!(node.authorized("node:1", &protocol));
+        assert!(!node.authorized("node:2", &protocol));
+        assert_eq!(node.sender("node:1"), 1);
+        for command in [setup(2), NodeMessage::Stop { round: 1 }] {
+            assert!(node.authorized("interface", &command));
+            assert!(!node.authorized("node:1", &command));
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn the_loop_handles_frames_during_a_computation() {
+        let network = Arc::new(MemoryNetwork::default());
+        let mut interface = network.register(INTERFACE_IP);
+        let addr = format!("{MEMORY_HOST}:0");
+        let (node, computed) = Node::new(INTERFACE_IP.to_string(), addr.clone(), network.clone());
+        let (frames, inbox) = channel(INBOX_SIZE);
+        let start = Instant::now();
+        let second = std::time::Duration::from_secs(1);
+        for _ in 0..cores() {
+            node.compute(&[(1_000_000, 10)], || ());
+        }
+        tokio::spawn(run(node, computed, inbox));
+        for msg in [
+            setup(1),
+            key(0),
+            NodeMessage::Reconstruct {
+                round: 2,
+                corrupt: None,
+            },
+        ] {
+            frames.send((None, msg.to_frame())).await.unwrap();
+        }
+        // The node has no share to reveal, it outputs at once.
+        let output = loop {
+            let frame = interface.recv().await.unwrap();
+            if let Ok(msg @ InterfaceMessage::Output { .. }) = InterfaceMessage::from_frame(&frame)
+            {
+                break msg;
+            }
+        };
+        assert!(matches!(output, InterfaceMessage::Output { round: 2, .. }));
+        assert!(start.elapsed() < second);
+    }
+
+    /// The next output of `round` the interface receives, with the index of its node.
+    async fn next_output(interface: &mut Receiver<Frame>, round: u32) -> (u16, ResultFields) {
+        loop {
+            let frame = interface.recv().await.unwrap();
+            match InterfaceMessage::from_frame(&frame) {
+                Ok(InterfaceMessage::Output {
+                    round: r,
+                    index,
+                    result,
+                }) if r == round => return (index, result),
+                _ => continue,
+            }
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_restarted_node_takes_part_in_the_reconstruction() {
+        let n = 4;
+        let addr = |i: u16| format!("{MEMORY_HOST}:{i}");
+        for protocol in protocol_names() {
+            let state = env::temp_dir().join(format!("state_{protocol}_{}", std::process::id()));
+            let network = Arc::new(MemoryNetwork::default());
+            let mut interface = network.register(INTERFACE_IP);
+            let spawn = |i: u16| {
+                let dir = Some(state.join(i.to_string()));
+                spawn_in_process(network.clone(), INTERFACE_IP.to_string(), i, dir)
+            };
+            for i in 0..n {
+                spawn(i).await;
+            }
+            let NodeMessage::Setup { setup } = setup(n) else {
+                unreachable!()
+            };
+            let setup = NodeMessage::Setup {
+                setup: Setup {
+                    f: 1,
+                    protocol: protocol.to_string(),
+                    ..setup
+                },
+            };
+            for i in 0..n {
+                network.send(&addr(i), setup.to_frame()).await.unwrap();
+            }
+            let deal = NodeMessage::DealThis { secret: 42 };
+            network.send(&addr(0), deal.to_frame()).await.unwrap();
+            for _ in 0..n {
+                assert!(!next_output(&mut interface, 1).await.1.is_err());
+            }
+            // The node 3 dies between the sharing and the reconstruction, then restarts.
+            network.unregister(&addr(3));
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            assert!(network.send(&addr(3), setup.to_frame()).await.is_err());
+            spawn(3).await;
+            // Without the node 0, d + 1 = 3 shares are only revealed with the one of the node 3.
+            let reconstruct = NodeMessage::Reconstruct {
+                round: 2,
+                corrupt: None,
+            };
+            for i in 1..n {
+                network
+                    .send(&addr(i), reconstruct.to_frame())
+                    .await
+                    .unwrap();
+            }
+            let mut outputs = Vec::new();
+            for _ in 1..n {
+                let output = next_output(&mut interface, 2);
+                let (index, result) =
+                    tokio::time::timeout(std::time::Duration::from_secs(60), output)
+                        .await
+                        .unwrap_or_else(|_| panic!("{protocol}: the reconstruction did not end"));
+                assert!(!result.is_err(), "{protocol}: node {index}");
+                assert!(result.decoded().is_none_or(|decoded| decoded.secret == 42));
+                outputs.push(index);
+            }
+            outputs.sort();
+            assert_eq!(outputs, [1, 2, 3]);
+            std::fs::remove_dir_all(state).unwrap();
+        }
+    }
+}
@@ -1,188 +1,1193 @@
-use crate::*;
+use super::*;
+use std::cmp::Ordering;
+use std::ops::Range;
 
-pub const MESSAGE_SIZE: usize = 1500;
+as_number!(
+    u8,
+    enum AvssSimplCode {
+        SHARE,
+        ACK,
+        REST,
+        NEWSHARE,
+    }
+);
 
-pub async fn broadcast<'a>(network: &Vec<ExternNode>, msg: Bytes<'a>) {
-    let message = Arc::from(Mutex::from(msg.to_vec()));
-    for node in network {
-        let addr = node.addr.clone();
-        let message = message.clone();
-        tokio::spawn(async move {
-            private_message(&addr, &message.lock().await).await;
-        });
+wire_struct!(
+    /// A share revealed for the reconstruction `reconstruction`, without its proof when the
+    /// nodes decode.
+    #[derive(Clone)]
+    struct RevealedShare {
+        reconstruction: u32,
+        index: u16,
+        share: Share,
+        proof: Option<Proof>,
+    }
+);
+
+payloads!(
+    /// Messages of `avss_simpl`, the ones of the dealer tagged with the round of its sharing.
+    enum AvssSimplMessage: AvssSimplCode {
+        /// The share of the node, sent by the dealer.
+        SHARE => Share { round: u32, proof: Proof, comm: Commitment, share: Share },
+        /// The ack of the node `index`, sent to the dealer of the sharing of `round`.
+        ACK => Ack { round: u32, index: u16, sign: Sign },
+        /// The broadcast of the dealer: the acks it gathered and the shares of the nodes which
+        /// did not acknowledge.
+        REST => Rest {
+            round: u32,
+            comm: Commitment,
+            signatures: Vec<(u16, Sign)>,
+            missing: Vec<Batch>,
+        },
+        NEWSHARE => NewShare { revealed: RevealedShare },
     }
+);
+
+/// Where a revealed share stands against the reconstruction of the node.
+#[derive(Debug, PartialEq)]
+enum ShareRound {
+    /// Of a reconstruction the node did not start yet, kept until it does.
+    Early,
+    Current,
+    /// Of a previous reconstruction, dropped.
+    Stale,
 }
 
-pub async fn deal(
-    node: Wrapped<Node>,
-    t: u16,
-    network: Vec<ExternNode>,
-    mut receiver: Receiver<DealerMessage>,
-    secret: u128,
-) {
-    let start = Instant::now();
-    node.lock().await.timer = Instant::now();
-    let n = network.len() as u16;
-    // println!("n = {n}, t = {t}");
-    let (comm, mut output) = compute_proof_and_shares(n, 2 * t as u32, secret);
-    let mut shares = HashMap::<u16, Batch>::new();
-    // println!("DEALING: Computing messages...");
-    let mut comm_parsed: [u8; MESSAGE_SIZE] = [0; MESSAGE_SIZE];
-    let comm_size = comm.write(&mut comm_parsed);
-    let comm_parsed = &comm_parsed[..comm_size];
-    let messages = (0..n)
-        .rev()
-        .map(|i| {
-            let (proof, share) = output.pop().unwrap();
-            let mut buf = [0; MESSAGE_SIZE * 2];
-            buf[0] = CommandCode::SHARE.into();
-            let mut index = proof.write(&mut buf[1..]) + 1;
-            for b in comm_parsed {
-                buf[index] = *b;
-                index += 1;
+impl ShareRound {
+    fn of(tag: u32, reconstruction: u32, step: Step) -> ShareRound {
+        if tag > reconstruction {
+            ShareRound::Early
+        } else if tag < reconstruction || step != Step::Reconstruct {
+            ShareRound::Stale
+        } else {
+            ShareRound::Current
+        }
+    }
+}
+
+/// Results of the computations of `avss_simpl`, see `Node::compute`. They are posted back boxed.
+#[allow(clippy::large_enum_variant)]
+enum Done {
+    Dealt {
+        round: u32,
+        start: Instant,
+        dealing: Dealing,
+    },
+    /// Whether the node accepts the share the dealer sent it.
+    Share {
+        round: u32,
+        start: Instant,
+        byz_comp: ByzComp,
+        accepted: bool,
+        share: Share,
+        proof: Proof,
+    },
+    /// The ack of the share of the node.
+    Signed {
+        round: u32,
+        start: Instant,
+        byz_comp: ByzComp,
+        sign: Sign,
+    },
+    /// Whether the ack of the node `index` is signed with its key.
+    Ack {
+        round: u32,
+        index: u16,
+        sign: Sign,
+        valid: bool,
+    },
+    /// The outcome of the checks of the `REST` broadcast.
+    Rest {
+        round: u32,
+        start: Instant,
+        code: ErrorCode,
+        comm: Commitment,
+        missing: Vec<Batch>,
+    },
+    /// Whether the share revealed by the node `index` is valid.
+    Revealed {
+        reconstruction: u32,
+        index: u16,
+        share: Share,
+        proof: Proof,
+        valid: bool,
+    },
+    Decoded {
+        reconstruction: u32,
+        decoded: Option<(Polynomial, Vec<u16>)>,
+    },
+}
+
+/// The sharing of the dealer while it gathers the acks of the nodes.
+struct Dealer {
+    round: u32,
+    byz_comp: ByzComp,
+    start: Instant,
+    comm: Commitment,
+    /// Shares of the nodes that did not acknowledge yet.
+    missing: HashMap<u16, Batch>,
+    signatures: Vec<(u16, Sign)>,
+}
+
+#[derive(Default)]
+pub struct AvssSimpl {
+    /// Round of the sharing the node is set up for. The messages of the dealer of a later one
+    /// wait in `early` for its `SETUP`.
+    sharing: u32,
+    early: Vec<(Option<u16>, AvssSimplMessage)>,
+    /// Whether the node received its share in the current sharing.
+    received: bool,
+    dealer: Option<Dealer>,
+    /// Reconstruction shares received without proof when decoding is enabled, with the
+    /// reconstruction they belong to.
+    decoding_shares: (u32, Vec<(u16, Share)>),
+    /// Whether a decoding runs, a share arriving meanwhile is decoded with the other late ones
+    /// once it is over.
+    decoding: bool,
+    /// The reconstruction and the number of shares of the last decoding.
+    attempt: (u32, usize),
+    /// `NEWSHARE` messages of a reconstruction the node did not start yet.
+    early_shares: Vec<RevealedShare>,
+}
+
+impl AvssSimpl {
+    pub const NAME: &'static str = "avss_simpl";
+
+    pub fn build() -> Box<dyn Protocol> {
+        Box::new(AvssSimpl::default())
+    }
+
+    /// Handles a message of the dealer of the sharing `round`, kept until the `SETUP` of a
+    /// later sharing and dropped for an earlier one or when another node sent it.
+    fn dealer_message(
+        &mut self,
+        node: &mut Node,
+        from: Option<u16>,
+        round: u32,
+        msg: AvssSimplMessage,
+    ) {
+        match round.cmp(&self.sharing) {
+            Ordering::Greater => return self.early.push((from, msg)),
+            Ordering::Less => return,
+            Ordering::Equal if node.step != Step::Sharing || node.im_done => return,
+            Ordering::Equal if !sent_by(from, node.dealer) => {
+                return eprintln!(
+                    "Node {}: message of the dealer sent by {from:?}",
+                    node.index
+                )
+            },
+            Ordering::Equal => (),
+        }
+        match msg {
+            AvssSimplMessage::Share {
+                proof, comm, share, ..
+            } if !self.received => {
+                self.received = true;
+                first_receiv(node, comm, share, proof)
+            },
+            AvssSimplMessage::Rest {
+                comm,
+                signatures,
+                missing,
+                ..
+            } => verify_and_output(node, comm, signatures, missing),
+            _ => (),
+        }
+    }
+
+    /// Checks the ack off the loop, the dealer keeps the first valid one of each node.
+    fn new_sign(&mut self, node: &mut Node, round: u32, i: u16, sign: Sign) {
+        let Some(dealer) = &self.dealer else {
+            return;
+        };
+        if dealer.round != round || !dealer.missing.contains_key(&i) {
+            return;
+        }
+        let key = node.network[i as usize].p_key.clone();
+        node.compute(&[], move || {
+            let valid = is_valid_sign(&key, &sign);
+            Done::Ack {
+                round,
+                index: i,
+                sign,
+                valid,
             }
-            share.write(&mut buf[index..]);
-            shares.insert(i, (i, proof, share));
-            (i as usize, buf)
-        })
-        .collect::<Vec<(usize, [u8; MESSAGE_SIZE * 2])>>();
-    node.lock().await.result.set(
-        TypeResultField::MessagesComputing,
-        start.elapsed().as_millis(),
-    );
-    for (i, msg) in &messages {
-        private_message(network[*i].addr(), msg).await;
-    }
-    let mut signatures = Vec::<(u16, Sign)>::new();
-    let deux_t_plus_un = 2 * t + 1;
-    loop {
-        match receiver.recv().await {
-            Some(m) => match m {
-                DealerMessage::Message(i, sign)
-                    if is_valid_sign(&network[i as usize].p_key, &sign) =>
-                {
-                    shares.remove(&i);
-                    signatures.push((i, sign));
-                    if signatures.len() == deux_t_plus_un as usize {
-                        break;
-                    }
+        });
+    }
+
+    /// Broadcasts the acks and the missing shares once `d + 1` nodes acknowledged.
+    fn acked(&mut self, node: &mut Node, round: u32, i: u16, sign: Sign) {
+        let Some(dealer) = self.dealer.as_mut().filter(|dealer| dealer.round == round) else {
+            return;
+        };
+        new_ack(&mut dealer.missing, &mut dealer.signatures, i, sign);
+        if dealer.signatures.len() <= node.d as usize {
+            return;
+        }
+        let mut dealer = self.dealer.take().unwrap();
+        let broadcast_timer = Instant::now();
+        let mut missing_shares = dealer.missing.into_values().collect::<Vec<Batch>>();
+        forge_rest(dealer.byz_comp, &mut dealer.signatures, &mut missing_shares);
+        node.result
+            .set(TypeResultField::Dealing, dealer.start.elapsed().as_millis());
+        node.result.set(
+            TypeResultField::BroadCasting,
+            broadcast_timer.elapsed().as_millis(),
+        );
+        let rest = AvssSimplMessage::Rest {
+            round,
+            comm: dealer.comm,
+            signatures: dealer.signatures,
+            missing: missing_shares,
+        };
+        node.broadcast(&protocol_message(AvssSimpl::NAME, &rest));
+    }
+
+    /// Sends the shares of the dealing and waits for the acks.
+    fn dealt(&mut self, node: &mut Node, round: u32, start: Instant, dealing: Dealing) {
+        if node.round != round || node.step != Step::Sharing {
+            return;
+        }
+        node.result.set(
+            TypeResultField::MessagesComputing,
+            start.elapsed().as_millis(),
+        );
+        for (ext_node, msg) in node.network.iter().zip(dealing.share_messages(round)) {
+            node.send(ext_node.addr(), &protocol_message(AvssSimpl::NAME, &msg));
+        }
+        let missing = dealing
+            .shares
+            .into_iter()
+            .map(|batch| (batch.0, batch))
+            .collect();
+        self.dealer = Some(Dealer {
+            round,
+            byz_comp: node.byz_comp,
+            start,
+            comm: dealing.comm,
+            missing,
+            signatures: Vec::new(),
+        });
+    }
+
+    fn new_share(&mut self, node: &mut Node, revealed: RevealedShare) {
+        match ShareRound::of(revealed.reconstruction, node.reconstruction, node.step) {
+            ShareRound::Early => return self.early_shares.push(revealed),
+            ShareRound::Stale => return,
+            ShareRound::Current => (),
+        }
+        let RevealedShare {
+            reconstruction,
+            index: i,
+            share,
+            proof,
+        } = revealed;
+        let proof = match proof {
+            _ if node.decoding => return self.new_decoding_share(node, reconstruction, i, share),
+            Some(proof) => proof,
+            None => return,
+        };
+        // The shares arriving once the node output are not verified.
+        let set = node.get_current_set();
+        if node.im_done || set.comm.is_none() || set.set.contains_key(&i) {
+            return;
+        }
+        let comm = set.get_comm().clone();
+        node.compute(&[(node.costs.verify, 1)], move || {
+            let valid = verify(&comm, i + 1, &share, &proof);
+            Done::Revealed {
+                reconstruction,
+                index: i,
+                share,
+                proof,
+                valid,
+            }
+        });
+    }
+
+    fn revealed(
+        &mut self,
+        node: &mut Node,
+        reconstruction: u32,
+        i: u16,
+        share: Share,
+        proof: Proof,
+    ) {
+        if node.im_done || !node.reconstructing(reconstruction) {
+            return;
+        }
+        node.save_share(i, share, proof);
+        if node.get_current_set().len() > node.d {
+            node.output(Step::Reconstruct, ErrorCode::OK)
+        }
+    }
+
+    /// Reconstruction without proofs, the shares are decoded once enough of them arrived.
+    fn new_decoding_share(&mut self, node: &mut Node, reconstruction: u32, i: u16, share: Share) {
+        let shares = Self::shares_of(&mut self.decoding_shares, reconstruction);
+        if shares.iter().any(|(j, _)| *j == i) {
+            return;
+        }
+        shares.push((i, share));
+        self.decode(node)
+    }
+
+    /// Decodes the shares of the current reconstruction off the loop, unless a decoding is
+    /// running or they did not change since the last one.
+    fn decode(&mut self, node: &mut Node) {
+        let (reconstruction, shares) = &self.decoding_shares;
+        let attempt = (*reconstruction, shares.len());
+        if self.decoding
+            || node.im_done
+            || !node.reconstructing(*reconstruction)
+            || self.attempt == attempt
+        {
+            return;
+        }
+        let (d, f) = (node.d as usize, node.f as usize);
+        if !Polynomial::decodable(shares.len(), d, f) {
+            return;
+        }
+        (self.decoding, self.attempt) = (true, attempt);
+        let (reconstruction, shares) = (*reconstruction, shares.clone());
+        node.compute(&[(node.costs.decode, 1)], move || Done::Decoded {
+            reconstruction,
+            decoded: Polynomial::decode(&shares, d, f),
+        });
+    }
+
+    fn decoded(
+        &mut self,
+        node: &mut Node,
+        reconstruction: u32,
+        decoded: Option<(Polynomial, Vec<u16>)>,
+    ) {
+        self.decoding = false;
+        match decoded {
+            Some((poly, wrong)) if !node.im_done && node.reconstructing(reconstruction) => {
+                node.decoded(&poly.secret(), &wrong);
+                node.output(Step::Reconstruct, ErrorCode::OK)
+            },
+            // The shares that arrived during the decoding.
+            _ => self.decode(node),
+        }
+    }
+
+    /// The decoding shares of `reconstruction`, the ones of the previous reconstruction are
+    /// dropped.
+    fn shares_of(
+        shares: &mut (u32, Vec<(u16, Share)>),
+        reconstruction: u32,
+    ) -> &mut Vec<(u16, Share)> {
+        if shares.0 != reconstruction {
+            *shares = (reconstruction, Vec::new());
+        }
+        &mut shares.1
+    }
+}
+
+impl Protocol for AvssSimpl {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    /// The acks of the previous sharing are dropped, the messages of the dealer of the new
+    /// one that arrived before the `SETUP` are handled.
+    fn setup(&mut self, node: &mut Node) {
+        self.sharing = node.round;
+        self.received = false;
+        self.dealer = None;
+        for (from, msg) in std::mem::take(&mut self.early) {
+            let round = match &msg {
+                AvssSimplMessage::Share { round, .. } | AvssSimplMessage::Rest { round, .. } => {
+                    *round
                 },
-                _ => (),
+                _ => continue,
+            };
+            self.dealer_message(node, from, round, msg);
+        }
+    }
+
+    fn deal(&mut self, node: &mut Node, secret: u128) {
+        node.timer = Instant::now();
+        let start = Instant::now();
+        let (byz_comp, n, d, victims, round) =
+            (node.byz_comp, node.n, node.d, node.victims, node.round);
+        let costs = [(node.costs.commit, 1), (node.costs.prove, n as usize)];
+        node.compute(&costs, move || Done::Dealt {
+            round,
+            start,
+            dealing: Dealing::new(byz_comp, n, d, victims, secret),
+        });
+    }
+
+    fn new_message(&mut self, node: &mut Node, from: Option<u16>, bytes: Bytes<'_>) {
+        let msg = match AvssSimplMessage::from_bytes(bytes) {
+            Ok(msg) => msg,
+            Err(e) => return eprintln!("Invalid {} message: {e}", Self::NAME),
+        };
+        match msg {
+            AvssSimplMessage::Share { round, .. } | AvssSimplMessage::Rest { round, .. } => {
+                self.dealer_message(node, from, round, msg)
             },
-            None => panic!("error during receiving phase"),
-        }
-    }    
-    let broadcast_timer = Instant::now();
-    let missing_shares = shares
-        .values()
-        .map(|(i, p, s)| (*i, p.clone(), s.clone()))
-        .collect::<Vec<Batch>>();
-    let mut buf = vec![0; BASE_CAPACITY];
-    let mut index = 1;
-    buf[0] = CommandCode::REST.into();
-    index += comm.write(&mut buf[index..]);
-    LittleEndian::write_u32(&mut buf[index..], signatures.len() as u32);
-    index += 4;
-    for (i, sign) in signatures.iter() {
-        LittleEndian::write_u16(&mut buf[index..], *i);
-        index += 2;
-        index += write_vec(&mut buf, index, sign) as usize;
-    }
-    LittleEndian::write_u32(&mut buf[index..], missing_shares.len() as u32);
-    index += 4;
-    for (i, proof, share) in missing_shares.iter() {
-        LittleEndian::write_u16(&mut buf[index..], *i);
-        index += 2;
-        index += proof.write(&mut buf[index..]);
-        index += share.write(&mut buf[index..]);
-        if (buf.len() as f32 * 0.8) as usize <= index {
-            buf.append(&mut vec![0; BASE_CAPACITY])
-        }
-    }
-    node.lock()
-        .await
-        .result
-        .set(TypeResultField::Dealing, start.elapsed().as_millis());
-    node.lock().await.result.set(
-        TypeResultField::BroadCasting,
-        broadcast_timer.elapsed().as_millis(),
-    );
-    broadcast(&network, trim_buf(&buf)).await;
-    // println!("------------------------------------------------------------------------------------------------------------------");
-    // println!("DEALING TIME: {:?}", start.elapsed());
-    // println!("------------------------------------------------------------------------------------------------------------------");
-}
-
-pub async fn verify_and_output(
-    node: Wrapped<Node>,
+            AvssSimplMessage::Ack { index, .. }
+            | AvssSimplMessage::NewShare {
+                revealed: RevealedShare { index, .. },
+            } if index >= node.n || !sent_by(from, index) => {
+                eprintln!("Node {}: message of {index} sent by {from:?}", node.index)
+            },
+            AvssSimplMessage::Ack { round, index, sign } => self.new_sign(node, round, index, sign),
+            AvssSimplMessage::NewShare { revealed } => self.new_share(node, revealed),
+        }
+    }
+
+    fn reconstruct(&mut self, node: &mut Node) {
+        for revealed in std::mem::take(&mut self.early_shares) {
+            self.new_share(node, revealed);
+        }
+        if node.get_current_set().comm.is_none() {
+            // The sharing failed, there is no share to reveal.
+            return node.output(Step::Reconstruct, ErrorCode::MissingShare);
+        }
+        if node.decoding {
+            let shares = Self::shares_of(&mut self.decoding_shares, node.reconstruction);
+            if !shares.iter().any(|(j, _)| *j == node.index) {
+                shares.push((node.index, node.my_share().clone()));
+            }
+        }
+        let byz_comp = node.act();
+        if byz_comp == ByzComp::Sleeper {
+            return;
+        }
+        let (index, share) = revealed_share(byz_comp, node.index, node.n, node.my_share());
+        let revealed = RevealedShare {
+            reconstruction: node.reconstruction,
+            index,
+            share,
+            proof: (!node.decoding).then(|| node.my_proof().clone()),
+        };
+        let msg = AvssSimplMessage::NewShare { revealed };
+        node.broadcast(&protocol_message(Self::NAME, &msg));
+    }
+
+    fn computed(&mut self, node: &mut Node, result: Computed) {
+        let Ok(done) = result.downcast::<Done>() else {
+            return;
+        };
+        match *done {
+            Done::Dealt {
+                round,
+                start,
+                dealing,
+            } => self.dealt(node, round, start, dealing),
+            Done::Share {
+                round,
+                start,
+                byz_comp,
+                accepted,
+                share,
+                proof,
+            } => share_checked(node, round, start, byz_comp, accepted, share, proof),
+            Done::Signed {
+                round,
+                start,
+                byz_comp,
+                sign,
+            } => send_acks(node, round, start, byz_comp, sign),
+            Done::Ack {
+                round,
+                index,
+                sign,
+                valid,
+            } => {
+                if valid {
+                    self.acked(node, round, index, sign)
+                }
+            },
+            Done::Rest {
+                round,
+                start,
+                code,
+                comm,
+                missing,
+            } => rest_checked(node, round, start, code, comm, missing),
+            Done::Revealed {
+                reconstruction,
+                index,
+                share,
+                proof,
+                valid,
+            } => {
+                if valid {
+                    self.revealed(node, reconstruction, index, share, proof)
+                }
+            },
+            Done::Decoded {
+                reconstruction,
+                decoded,
+            } => self.decoded(node, reconstruction, decoded),
+        }
+    }
+}
+
+/// The shares of the committed polynomial, and what the dealer sends in `SHARE`, which differs
+/// for the last `victims` nodes when the dealer is byzantine.
+struct Dealing {
     comm: Commitment,
-    signatures: Vec<(u16, Sign)>,
-    missing_shares: Vec<Batch>,
+    /// Commitment of another polynomial, sent to the victims of an equivocating dealer.
+    forged_comm: Option<Commitment>,
+    shares: Vec<Batch>,
+    sent: Vec<(Proof, Share)>,
+    victims: Range<u16>,
+}
+
+impl Dealing {
+    fn new(byz_comp: ByzComp, n: u16, d: u16, victims: u16, secret: u128) -> Dealing {
+        let (comm, output) = compute_proof_and_shares(n, d as u32, secret);
+        let forged = (byz_comp == ByzComp::Equivocation)
+            .then(|| compute_proof_and_shares(n, d as u32, secret.wrapping_add(1)));
+        let victims = n - victims..n;
+        let shares = output
+            .into_iter()
+            .enumerate()
+            .map(|(i, (proof, share))| (i as u16, proof, share))
+            .collect::<Vec<Batch>>();
+        let sent = shares
+            .iter()
+            .map(|(i, proof, share)| match &forged {
+                Some((_, output)) if victims.contains(i) => output[*i as usize].clone(),
+                None if byz_comp == ByzComp::InvalidShares && victims.contains(i) => {
+                    (proof.clone(), share.tampered())
+                },
+                _ => (proof.clone(), share.clone()),
+            })
+            .collect();
+        Dealing {
+            comm,
+            forged_comm: forged.map(|(comm, _)| comm),
+            shares,
+            sent,
+            victims,
+        }
+    }
+
+    fn share_messages(&self, round: u32) -> Vec<AvssSimplMessage> {
+        self.sent
+            .iter()
+            .enumerate()
+            .map(|(i, (proof, share))| {
+                let comm = match &self.forged_comm {
+                    Some(forged) if self.victims.contains(&(i as u16)) => forged,
+                    _ => &self.comm,
+                };
+                AvssSimplMessage::Share {
+                    round,
+                    proof: proof.clone(),
+                    comm: comm.clone(),
+                    share: share.clone(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// The acks a node sends to the dealer for its share, tampered with when it is byzantine.
+fn acks(byz_comp: ByzComp, index: u16, n: u16, sign: Sign) -> Vec<(u16, Sign)> {
+    match byz_comp {
+        ByzComp::ForgedAcks | ByzComp::Random => {
+            vec![(index, (0..sign.len()).map(|_| rng().gen()).collect())]
+        },
+        ByzComp::ForeignAcks => vec![((index + 1) % n, sign)],
+        ByzComp::DuplicateAcks => vec![(index, sign); n as usize],
+        _ => vec![(index, sign)],
+    }
+}
+
+/// Keeps the first ack of each node, whose share is then no longer missing. The ack was
+/// checked against the key of the node.
+fn new_ack(
+    missing: &mut HashMap<u16, Batch>,
+    signatures: &mut Vec<(u16, Sign)>,
+    i: u16,
+    sign: Sign,
 ) {
-    let mut node = node.lock().await;
-    let start = Instant::now();
-    let mut shares_set: Vec<bool> = vec![false; node.n as usize];
-    if signatures.len() as u16 != node.t*2 + 1 {
-        node.output(Step::Sharing, ErrorCode::UnvalidSigns).await;
-        println!("ERROR 1");
-        return;
+    if missing.remove(&i).is_some() {
+        signatures.push((i, sign));
+    }
+}
+
+/// Tampers with the `REST` broadcast of a byzantine dealer.
+fn forge_rest(byz_comp: ByzComp, signatures: &mut [(u16, Sign)], missing_shares: &mut Vec<Batch>) {
+    match byz_comp {
+        ByzComp::DuplicateSigns if signatures.len() > 1 => {
+            signatures[signatures.len() - 1] = signatures[0].clone()
+        },
+        ByzComp::InvalidSigns if !signatures.is_empty() => signatures[0].1[0] ^= 1,
+        ByzComp::OmitShares => {
+            missing_shares.pop();
+        },
+        _ => (),
+    }
+}
+
+/// Whether the node `index` acknowledges the share it received from the dealer.
+fn accepts_share(comm: &Commitment, d: u16, index: u16, share: &Share, proof: &Proof) -> bool {
+    deg_check(comm, d as usize) && verify(comm, index + 1, share, proof)
+}
+
+/// Checks the `REST` broadcast of the dealer on the node `index`, which holds `own` if it
+/// acknowledged its share.
+fn check_rest(
+    network: &[ExternNode],
+    d: u16,
+    index: u16,
+    own: Option<&(Share, Proof)>,
+    comm: &Commitment,
+    signatures: &[(u16, Sign)],
+    missing_shares: &[Batch],
+) -> ErrorCode {
+    let mut shares_set: Vec<bool> = vec![false; network.len()];
+    if !deg_check(comm, d as usize) {
+        return ErrorCode::UnvalidDegree;
+    }
+    if signatures.len() as u16 != d + 1 {
+        return ErrorCode::UnvalidSigns;
     }
     for (i, sign) in signatures {
-        if !is_valid_sign(&node.network[i as usize].p_key, &sign) || shares_set[i as usize] {
-            node.output(Step::Sharing, ErrorCode::UnvalidSigns).await;
-            return;
+        if *i as usize >= network.len()
+            || !is_valid_sign(&network[*i as usize].p_key, sign)
+            || shares_set[*i as usize]
+        {
+            return ErrorCode::UnvalidSigns;
         }
-        shares_set[i as usize] = true;
+        shares_set[*i as usize] = true;
     }
-    if !batch_verify(&comm, &missing_shares) {
-        println!("ERROR 2");
-        node.output(Step::Sharing, ErrorCode::UnvalidShares).await;
-        return;
+    if shares_set[index as usize] {
+        match own {
+            Some((share, proof)) if verify(comm, index + 1, share, proof) => (),
+            _ => return ErrorCode::IncoherentCommitment,
+        }
     }
-    for (i, p, s) in missing_shares {
-        if shares_set[i as usize] {
-            eprintln!("ERROR: 3");
-            node.output(Step::Sharing, ErrorCode::IncoherentBatch).await;
-            return;
+    if !batch_verify(comm, missing_shares) {
+        return ErrorCode::UnvalidShares;
+    }
+    for (i, _, _) in missing_shares {
+        if *i as usize >= network.len() || shares_set[*i as usize] {
+            return ErrorCode::IncoherentBatch;
         }
-        shares_set[i as usize] = true;
-        node.save_share(i, s, p);
+        shares_set[*i as usize] = true;
     }
     if shares_set.contains(&false) {
-        eprintln!("ERROR: 4");
-        node.output(Step::Sharing, ErrorCode::MissingShare).await;
+        return ErrorCode::MissingShare;
+    }
+    ErrorCode::OK
+}
+
+/// Checks the `REST` broadcast of the dealer off the loop, see `rest_checked`.
+pub fn verify_and_output(
+    node: &mut Node,
+    comm: Commitment,
+    signatures: Vec<(u16, Sign)>,
+    missing_shares: Vec<Batch>,
+) {
+    let start = Instant::now();
+    let own = node.get_current_set().set.get(&node.index).cloned();
+    let (network, d, index, round) = (node.get_current_network(), node.d, node.index, node.round);
+    let costs = [
+        (node.costs.verify_sign, signatures.len()),
+        (node.costs.verify, missing_shares.len() + 1),
+    ];
+    node.compute(&costs, move || {
+        let code = check_rest(
+            &network,
+            d,
+            index,
+            own.as_ref(),
+            &comm,
+            &signatures,
+            &missing_shares,
+        );
+        Done::Rest {
+            round,
+            start,
+            code,
+            comm,
+            missing: missing_shares,
+        }
+    });
+}
+
+/// Outputs the sharing once the `REST` broadcast is checked, with the missing shares saved.
+fn rest_checked(
+    node: &mut Node,
+    round: u32,
+    start: Instant,
+    code: ErrorCode,
+    comm: Commitment,
+    missing_shares: Vec<Batch>,
+) {
+    if node.round != round || node.step != Step::Sharing {
         return;
     }
+    if code != ErrorCode::OK {
+        println!(
+            "Node {}: invalid broadcast of the dealer, {code:?}",
+            node.index
+        );
+        return node.output(Step::Sharing, code);
+    }
+    for (i, p, s) in missing_shares {
+        node.save_share(i, s, p);
+    }
     node.get_current_set_mut().set_comm(comm);
-    // println!("output of {}: {:?}, duration: {:?}", node.index, node.my_share().share, start.elapsed());
     node.result
         .set(TypeResultField::Verify, start.elapsed().as_millis());
-    node.output(Step::Sharing, ErrorCode::OK).await;
+    node.output(Step::Sharing, ErrorCode::OK);
 }
 
-pub async fn first_receiv(node: Wrapped<Node>, comm: Commitment, share: Share, proof: Proof) {
+/// Checks the share the dealer sent off the loop, see `share_checked`.
+pub fn first_receiv(node: &mut Node, comm: Commitment, share: Share, proof: Proof) {
     let start = Instant::now();
-    let mut node = node.lock().await;
-    if node.byz_comp == ByzComp::Sleeper {
+    let byz_comp = node.act();
+    if byz_comp == ByzComp::Sleeper {
         return;
     }
-    if deg_check(&comm, 2 * node.t as usize) && verify(&comm, node.index + 1, &share, &proof) {
-        let sign = node.sign();
-        let mut buf = [0; BASE_CAPACITY];
-        buf[0] = CommandCode::ACK.into();
-        LittleEndian::write_u16(&mut buf[1..], node.index);
-        write_bytes(&mut buf[3..], &sign);
-        private_message(node.network[node.dealer as usize].addr(), &buf).await;
-        let i = node.index;
-        node.save_share(i, share, proof);
-        // println!(
-        //     "Node {} fisnished to sign, duration: {:?}",
-        //     node.index,
-        //     start.elapsed()
-        // );
-    } else {
-        println!("Node {}: I received invalid share.", node.index);
+    let (d, index, round) = (node.d, node.index, node.round);
+    node.compute(&[(node.costs.verify, 1)], move || {
+        let accepted = accepts_share(&comm, d, index, &share, &proof);
+        Done::Share {
+            round,
+            start,
+            byz_comp,
+            accepted,
+            share,
+            proof,
+        }
+    });
+}
+
+/// Saves an accepted share and signs the ack off the loop, see `send_acks`.
+fn share_checked(
+    node: &mut Node,
+    round: u32,
+    start: Instant,
+    byz_comp: ByzComp,
+    accepted: bool,
+    share: Share,
+    proof: Proof,
+) {
+    if node.round != round || node.step != Step::Sharing {
+        return;
+    }
+    if !accepted {
+        node.result
+            .set(TypeResultField::FirstReceiv, start.elapsed().as_millis());
+        return println!("Node {}: I received invalid share.", node.index);
+    }
+    node.save_share(node.index, share, proof);
+    let keys = node.keys.clone();
+    node.compute(&[(node.costs.sign, 1)], move || Done::Signed {
+        round,
+        start,
+        byz_comp,
+        sign: sign(&keys),
+    });
+}
+
+fn send_acks(node: &mut Node, round: u32, start: Instant, byz_comp: ByzComp, sign: Sign) {
+    if node.round != round || node.step != Step::Sharing {
+        return;
     }
     node.result
         .set(TypeResultField::FirstReceiv, start.elapsed().as_millis());
+    let dealer = node.network[node.dealer as usize].addr.clone();
+    for (index, sign) in acks(byz_comp, node.index, node.n, sign) {
+        let ack = AvssSimplMessage::Ack { round, index, sign };
+        node.send(&dealer, &protocol_message(AvssSimpl::NAME, &ack));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const N: u16 = 7;
+    const D: u16 = 4;
+    const VICTIMS: u16 = 2;
+    /// The last nodes are the byzantine receivers.
+    const NB_BYZ: u16 = 2;
+
+    type Outcome = (Vec<Option<(Share, Proof)>>, Vec<ErrorCode>, Commitment);
+
+    /// Shares with a dealer behaving as `dealer` and byzantine receivers behaving as
+    /// `receivers`. Nodes acknowledge the shares passing the checks of `first_receiv`, the acks of
+    /// the last nodes reach the dealer first. Returns the shares kept by the nodes, the outcome of
+    /// the `REST` broadcast on each of them and the commitment.
+    fn sharing(dealer: ByzComp, receivers: ByzComp) -> Outcome {
+        let keys = (0..N)
+            .map(|_| KeyPair::generate(&rcgen::PKCS_ED25519).expect("Failed to construct key"))
+            .collect::<Vec<_>>();
+        let network = keys
+            .iter()
+            .map(|k| ExternNode::new(k.public_key_der(), String::new()))
+            .collect::<Vec<_>>();
+        let dealing = Dealing::new(dealer, N, D, VICTIMS, 42);
+        let mut own = vec![None; N as usize];
+        let mut missing = dealing
+            .shares
+            .iter()
+            .map(|(i, p, s)| (*i, (*i, p.clone(), s.clone())))
+            .collect::<HashMap<u16, Batch>>();
+        let mut signatures = Vec::new();
+        for (i, (proof, share)) in dealing.sent.iter().enumerate().rev() {
+            let comm = match &dealing.forged_comm {
+                Some(comm) if dealing.victims.contains(&(i as u16)) => comm,
+                _ => &dealing.comm,
+            };
+            if accepts_share(comm, D, i as u16, share, proof) {
+                own[i] = Some((share.clone(), proof.clone()));
+                let byz_comp = if i as u16 >= N - NB_BYZ {
+                    receivers
+                } else {
+                    ByzComp::Honnest
+                };
+                for (j, sign) in acks(byz_comp, i as u16, N, sign(&keys[i])) {
+                    let known = (j as usize) < network.len();
+                    if signatures.len() <= D as usize
+                        && known
+                        && is_valid_sign(&network[j as usize].p_key, &sign)
+                    {
+                        new_ack(&mut missing, &mut signatures, j, sign);
+                    }
+                }
+            }
+        }
+        let mut missing_shares = missing.into_values().collect();
+        forge_rest(dealer, &mut signatures, &mut missing_shares);
+        let codes = (0..N)
+            .map(|i| {
+                check_rest(
+                    &network,
+                    D,
+                    i,
+                    own[i as usize].as_ref(),
+                    &dealing.comm,
+                    &signatures,
+                    &missing_shares,
+                )
+            })
+            .collect();
+        (own, codes, dealing.comm)
+    }
+
+    #[test]
+    fn honest_dealer() {
+        let (own, codes, _) = sharing(ByzComp::Honnest, ByzComp::Honnest);
+        assert!(own.iter().all(Option::is_some));
+        assert!(codes.iter().all(|c| *c == ErrorCode::OK));
+    }
+
+    #[test]
+    fn invalid_shares_are_recovered_from_the_broadcast() {
+        let (own, codes, _) = sharing(ByzComp::InvalidShares, ByzComp::Honnest);
+        assert!(own[(N - VICTIMS) as usize..].iter().all(Option::is_none));
+        assert!(codes.iter().all(|c| *c == ErrorCode::OK));
+    }
+
+    #[test]
+    fn equivocation_is_detected_by_the_victims() {
+        let (_, codes, _) = sharing(ByzComp::Equivocation, ByzComp::Honnest);
+        let (honest, victims) = codes.split_at((N - VICTIMS) as usize);
+        assert!(honest.iter().all(|c| *c == ErrorCode::OK));
+        assert!(victims
+            .iter()
+            .all(|c| *c == ErrorCode::IncoherentCommitment));
+    }
+
+    /// The node `index` of a network of `N` nodes, with the results of its computations.
+    fn test_node(index: u16) -> (Node, UnboundedReceiver<Computed>) {
+        let (mut node, computed) = Node::new(
+            String::new(),
+            String::new(),
+            Arc::new(MemoryNetwork::default()),
+        );
+        (node.n, node.d, node.index) = (N, D, index);
+        (node, computed)
+    }
+
+    async fn next_done(computed: &mut UnboundedReceiver<Computed>) -> Done {
+        let result = computed
+            .recv()
+            .await
+            .expect("The node dropped its computations");
+        let Ok(done) = result.downcast::<Done>() else {
+            panic!("Not a result of avss_simpl");
+        };
+        *done
+    }
+
+    #[tokio::test]
+    async fn first_receiv_checks_the_share_and_the_degree() {
+        let (mut node, mut computed) = test_node(2);
+        let dealing = Dealing::new(ByzComp::Honnest, N, D, VICTIMS, 42);
+        let (proof, share) = dealing.sent[2].clone();
+        let other = dealing.sent[3].1.clone();
+        for (comm, share, expected) in [
+            (dealing.comm.clone(), share.clone(), true),
+            (dealing.comm.clone(), share.tampered(), false),
+            (dealing.comm.clone(), other, false),
+            (
+                dealing.comm.clone().announcing(D as u32 + 1),
+                share.clone(),
+                false,
+            ),
+        ] {
+            first_receiv(&mut node, comm, share, proof.clone());
+            let Done::Share { accepted, .. } = next_done(&mut computed).await else {
+                panic!("The share was not checked");
+            };
+            assert_eq!(accepted, expected);
+        }
+        node.byz_comp = ByzComp::Sleeper;
+        first_receiv(&mut node, dealing.comm.clone(), share, proof);
+        assert!(computed.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn verify_and_output_checks_the_broadcast() {
+        let keys = (0..N)
+            .map(|_| KeyPair::generate(&rcgen::PKCS_ED25519).expect("Failed to construct key"))
+            .collect::<Vec<_>>();
+        let (mut node, mut computed) = test_node(0);
+        node.network = keys
+            .iter()
+            .map(|k| ExternNode::new(k.public_key_der(), String::new()))
+            .collect();
+        let dealing = Dealing::new(ByzComp::Honnest, N, D, VICTIMS, 42);
+        let (proof, share) = dealing.sent[0].clone();
+        node.save_share(0, share, proof);
+        let signatures = (0..=D)
+            .map(|i| (i, sign(&keys[i as usize])))
+            .collect::<Vec<_>>();
+        let missing = dealing.shares[D as usize + 1..].to_vec();
+        let mut forged = signatures.clone();
+        forged[1].1 = sign(&keys[0]);
+        let mut tampered = missing.clone();
+        tampered[0].2 = tampered[0].2.tampered();
+        for (signatures, missing, expected) in [
+            (signatures.clone(), missing.clone(), ErrorCode::OK),
+            (forged, missing.clone(), ErrorCode::UnvalidSigns),
+            (signatures.clone(), tampered, ErrorCode::UnvalidShares),
+            (signatures, missing[1..].to_vec(), ErrorCode::MissingShare),
+        ] {
+            verify_and_output(&mut node, dealing.comm.clone(), signatures, missing);
+            let Done::Rest { code, .. } = next_done(&mut computed).await else {
+                panic!("The broadcast was not checked");
+            };
+            assert_eq!(code, expected);
+        }
+    }
+
+    #[test]
+    fn out_of_range_indices_are_rejected() {
+        // The dealer opens its polynomial on one more point than there are nodes.
+        let (comm, output) = compute_proof_and_shares(N + 1, D as u32, 42);
+        let key = KeyPair::generate(&rcgen::PKCS_ED25519).expect("Failed to construct key");
+        let network = vec![ExternNode::new(key.public_key_der(), String::new()); N as usize];
+        let mut signatures = (0..=D).map(|i| (i, sign(&key))).collect::<Vec<_>>();
+        let mut missing_shares = output
+            .iter()
+            .enumerate()
+            .skip(D as usize + 1)
+            .map(|(i, (proof, share))| (i as u16, proof.clone(), share.clone()))
+            .collect::<Vec<Batch>>();
+        let own = (output[0].1.clone(), output[0].0.clone());
+        let check = |signatures: &[(u16, Sign)], missing_shares: &[Batch]| {
+            check_rest(
+                &network,
+                D,
+                0,
+                Some(&own),
+                &comm,
+                signatures,
+                missing_shares,
+            )
+        };
+        assert_eq!(
+            check(&signatures, &missing_shares),
+            ErrorCode::IncoherentBatch
+        );
+        missing_shares.pop();
+        assert_eq!(check(&signatures, &missing_shares), ErrorCode::OK);
+        signatures[0].0 = u16::MAX;
+        assert_eq!(check(&signatures, &missing_shares), ErrorCode::UnvalidSigns);
+    }
+
+    #[test]
+    fn forged_signatures_are_rejected() {
+        for byz_comp in [ByzComp::DuplicateSigns, ByzComp::InvalidSigns] {
+            let (_, codes, _) = sharing(byz_comp, ByzComp::Honnest);
+            assert!(codes.iter().all(|c| *c == ErrorCode::UnvalidSigns));
+        }
+    }
+
+    #[test]
+    fn omitted_share_is_missing() {
+        let (_, codes, _) = sharing(ByzComp::OmitShares, ByzComp::Honnest);
+        assert!(codes.iter().all(|c| *c == ErrorCode::MissingShare));
+    }
+
+    #[test]
+    fn byzantine_acks_are_ignored() {
+        for byz_comp in [
+            ByzComp::ForgedAcks,
+            ByzComp::ForeignAcks,
+            ByzComp::DuplicateAcks,
+        ] {
+            let (_, codes, _) = sharing(ByzComp::Honnest, byz_comp);
+            assert!(codes.iter().all(|c| *c == ErrorCode::OK));
+        }
+    }
+
+    #[test]
+    fn invalid_messages_are_rejected() {
+        let dealing = Dealing::new(ByzComp::Honnest, N, D, VICTIMS, 42);
+        let (proof, share) = dealing.sent[0].clone();
+        let revealed = RevealedShare {
+            reconstruction: 4,
+            index: 5,
+            share: share.clone(),
+            proof: Some(proof.clone()),
+        };
+        let messages = [
+            dealing.share_messages(2).remove(0),
+            AvssSimplMessage::Ack {
+                round: 2,
+                index: 3,
+                sign: vec![7; 64],
+            },
+            AvssSimplMessage::Rest {
+                round: 2,
+                comm: dealing.comm.clone(),
+                signatures: vec![(1, vec![7; 64])],
+                missing: vec![(2, proof, share)],
+            },
+            AvssSimplMessage::NewShare { revealed },
+        ];
+        for msg in messages {
+            let bytes = msg.to_bytes();
+            assert!(AvssSimplMessage::from_bytes(&bytes).is_ok());
+            for len in (0..bytes.len()).step_by(bytes.len() / 50 + 1) {
+                assert!(AvssSimplMessage::from_bytes(&bytes[..len]).is_err());
+            }
+            assert!(AvssSimplMessage::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+            let mut longer = bytes.clone();
+            longer.push(0);
+            assert!(AvssSimplMessage::from_bytes(&longer).is_err());
+        }
+        assert!(AvssSimplMessage::from_bytes(&[200]).is_err());
+    }
+
+    #[test]
+    fn rest_holds_a_large_committee() {
+        let n = 500;
+        let (comm, output) = compute_proof_and_shares(n, D as u32, 42);
+        let key = KeyPair::generate(&rcgen::PKCS_ED25519).expect("Failed to construct key");
+        let signatures = (0..n).map(|i| (i, sign(&key))).collect::<Vec<_>>();
+        let missing_shares = output
+            .into_iter()
+            .enumerate()
+            .map(|(i, (proof, share))| (i as u16, proof, share))
+            .collect::<Vec<Batch>>();
+        let rest = AvssSimplMessage::Rest {
+            round: 1,
+            comm,
+            signatures: signatures.clone(),
+            missing: missing_shares.clone(),
+        }
+        .to_bytes();
+        assert_eq!(AvssSimplCode::from(rest[0]), AvssSimplCode::REST);
+        let Ok(AvssSimplMessage::Rest {
+            round: 1,
+            comm: read_comm,
+            signatures: read_signatures,
+            missing: read_shares,
+        }) = AvssSimplMessage::from_bytes(&rest)
+        else {
+            panic!("Invalid REST message")
+        };
+        assert!(deg_check(&read_comm, D as usize));
+        assert_eq!(read_signatures, signatures);
+        assert_eq!(read_shares.len(), n as usize);
+        assert!(read_shares
+            .iter()
+            .zip(&missing_shares)
+            .all(|((i, _, a), (j, _, b))| i == j && a == b));
+        assert!(batch_verify(&read_comm, &read_shares));
+    }
+
+    #[tokio::test]
+    async fn shares_of_unknown_nodes_are_dropped() {
+        let (mut node, _computed) = Node::new(
+            String::new(),
+            String::new(),
+            Arc::new(MemoryNetwork::default()),
+        );
+        (node.n, node.decoding) = (N, true);
+        node.start_reconstruct(1, None);
+        let mut avss = AvssSimpl::default();
+        for index in [N, u16::MAX] {
+            let msg = AvssSimplMessage::NewShare {
+                revealed: RevealedShare {
+                    reconstruction: 1,
+                    index,
+                    share: Share::random(),
+                    proof: None,
+                },
+            };
+            avss.new_message(&mut node, None, &msg.to_bytes());
+        }
+        assert!(avss.decoding_shares.1.is_empty());
+    }
+
+    #[test]
+    fn shares_of_other_reconstructions_are_not_mixed() {
+        assert_eq!(ShareRound::of(4, 4, Step::Reconstruct), ShareRound::Current);
+        assert_eq!(ShareRound::of(6, 4, Step::Reconstruct), ShareRound::Early);
+        assert_eq!(ShareRound::of(6, 4, Step::Sharing), ShareRound::Early);
+        assert_eq!(ShareRound::of(2, 4, Step::Reconstruct), ShareRound::Stale);
+        // Late share of the last reconstruction while the node shares again.
+        assert_eq!(ShareRound::of(4, 4, Step::Sharing), ShareRound::Stale);
+        let mut shares = (0, Vec::new());
+        AvssSimpl::shares_of(&mut shares, 4).push((0, Share::random()));
+        assert!(AvssSimpl::shares_of(&mut shares, 6).is_empty());
+    }
+
+    #[test]
+    fn byzantine_reconstruction_shares_are_rejected() {
+        let (own, _, comm) = sharing(ByzComp::Honnest, ByzComp::Honnest);
+        for byz_comp in [ByzComp::WrongShares, ByzComp::ForeignShares] {
+            let mut accepted = HashMap::new();
+            for (i, (share, proof)) in own.iter().map(|o| o.as_ref().unwrap()).enumerate().rev() {
+                let byzantine = i as u16 >= N - NB_BYZ;
+                let (j, share) = revealed_share(
+                    if byzantine {
+                        byz_comp
+                    } else {
+                        ByzComp::Honnest
+                    },
+                    i as u16,
+                    N,
+                    share,
+                );
+                if !accepted.contains_key(&j) && verify(&comm, j + 1, &share, proof) {
+                    accepted.insert(j, share);
+                }
+            }
+            let points = accepted.into_iter().collect::<Vec<_>>();
+            assert!(points.iter().all(|(j, _)| *j < N - NB_BYZ));
+            let poly = Polynomial::interpolate(&points[..D as usize + 1]);
+            assert_eq!(poly.secret().to_u128(), 42);
+        }
+    }
 }
@@ -0,0 +1,742 @@
+use super::*;
+use std::cmp::Ordering;
+
+as_number!(
+    u8,
+    enum BivariateCode {
+        SEND,
+        ECHO,
+        READY,
+        NEWSHARE,
+    }
+);
+
+payloads!(
+    /// Messages of `bivariate_avss`, all of them tagged with the round of their sharing.
+    enum BivariateMessage: BivariateCode {
+        /// The row and the column of the node, sent by the dealer.
+        SEND => Send {
+            round: u32,
+            row: Polynomial,
+            column: Polynomial,
+        },
+        /// Points of the node `index` on the row and on the column of the receiver.
+        ECHO => Echo {
+            round: u32,
+            index: u16,
+            points: (Share, Share),
+        },
+        READY => Ready {
+            round: u32,
+            index: u16,
+            points: (Share, Share),
+        },
+        NEWSHARE => NewShare {
+            round: u32,
+            reconstruction: u32,
+            index: u16,
+            share: Share,
+        },
+    }
+);
+
+impl BivariateMessage {
+    fn round(&self) -> u32 {
+        match self {
+            BivariateMessage::Send { round, .. }
+            | BivariateMessage::Echo { round, .. }
+            | BivariateMessage::Ready { round, .. }
+            | BivariateMessage::NewShare { round, .. } => *round,
+        }
+    }
+}
+
+type Points = Vec<(u16, Share)>;
+
+/// The row and the column of a node.
+type CrossLines = (Polynomial, Polynomial);
+
+/// Received points, indexed by their sender, on the row and on the column of the node.
+type CrossPoints = HashMap<u16, (Share, Share)>;
+
+/// State of the sharing the node is set up for. Its messages can arrive after the output of
+/// the node, they are then ignored.
+#[derive(Default)]
+struct Session {
+    row: Option<Polynomial>,
+    column: Option<Polynomial>,
+    echoes: CrossPoints,
+    readies: CrossPoints,
+    ready_sent: bool,
+    /// Whether the row and the column are being decoded, and from how many points the last
+    /// decoding started.
+    recovering: bool,
+    tried: usize,
+    done: bool,
+    /// Reception of the first message of the sharing.
+    timer: Option<Instant>,
+}
+
+impl Session {
+    /// Points of the row and of the column of the node, from the echoes or from the readies.
+    fn points(from: &CrossPoints) -> (Points, Points) {
+        from.iter()
+            .map(|(i, (r, c))| ((*i, r.clone()), (*i, c.clone())))
+            .unzip()
+    }
+
+    /// Whether the points of the node `j` are on the row and on the column of the node, never
+    /// before they are known.
+    fn agrees(&self, j: u16, (r, c): &(Share, Share)) -> bool {
+        match (&self.row, &self.column) {
+            (Some(row), Some(column)) => row.eval(j) == *r && column.eval(j) == *c,
+            _ => false,
+        }
+    }
+
+    /// Number of received points consistent with the row and the column of the node.
+    fn agreeing(&self, from: &CrossPoints) -> usize {
+        from.iter().filter(|(j, p)| self.agrees(**j, p)).count()
+    }
+
+    /// Keeps the points of the node `j` unless it already sent some. Points agreeing with the
+    /// row and the column replace wrong ones, which another node may have sent in its name
+    /// when the senders are not authenticated.
+    fn receive(&mut self, code: BivariateCode, j: u16, points: (Share, Share)) -> bool {
+        let from = match code {
+            BivariateCode::ECHO => &self.echoes,
+            _ => &self.readies,
+        };
+        if from
+            .get(&j)
+            .is_some_and(|old| self.agrees(j, old) || !self.agrees(j, &points))
+        {
+            return false;
+        }
+        match code {
+            BivariateCode::ECHO => self.echoes.insert(j, points),
+            _ => self.readies.insert(j, points),
+        };
+        true
+    }
+
+    /// Whether the node sends its ready points: `⌈(n + t + 1) / 2⌉` echoes or `t + 1` readies
+    /// agree with its row and its column.
+    fn ready_due(&self, n: usize, t: usize) -> bool {
+        !self.ready_sent
+            && (self.agreeing(&self.echoes) >= (n + t + 2) / 2 || self.agreeing(&self.readies) > t)
+    }
+
+    /// Whether the node outputs its share: it sent its ready points and `2t + 1` readies agree.
+    fn complete(&self, t: usize) -> bool {
+        self.ready_sent && self.agreeing(&self.readies) > 2 * t
+    }
+
+    /// The points to recover the row and the column of the node from when the dealer did not
+    /// send them, once they are enough to correct `t` wrong ones, see `recover`.
+    fn to_complete(&self, t: usize) -> Option<(Points, Points)> {
+        if self.row.is_some() {
+            return None;
+        }
+        let from = if self.readies.len() > self.echoes.len() {
+            &self.readies
+        } else {
+            &self.echoes
+        };
+        Polynomial::decodable(from.len(), t, t).then(|| Self::points(from))
+    }
+
+    /// Decodes a row and a column from their points, the up to `t` wrong points are corrected.
+    fn recover((row, column): (Points, Points), degree: usize, t: usize) -> Option<CrossLines> {
+        match (
+            Polynomial::decode(&row, degree, t),
+            Polynomial::decode(&column, degree, t),
+        ) {
+            (Some((row, _)), Some((column, _))) => Some((row, column)),
+            _ => None,
+        }
+    }
+}
+
+/// Results of the computations of `bivariate_avss`, see `Node::compute`.
+enum Done {
+    Dealt {
+        start: Instant,
+        messages: Vec<BivariateMessage>,
+    },
+    /// The points the node sends in `code`, to each node in order. `start` is the reception of
+    /// the row and the column for the echoes.
+    Points {
+        code: BivariateCode,
+        round: u32,
+        start: Option<Instant>,
+        points: Vec<(Share, Share)>,
+    },
+    /// The row and the column decoded from the points, see `Session::recover`.
+    Recovered {
+        round: u32,
+        lines: Option<CrossLines>,
+    },
+    Decoded {
+        reconstruction: u32,
+        decoded: Option<(Polynomial, Vec<u16>)>,
+    },
+}
+
+/// Bivariate AVSS in the style of Cachin, Kursawe, Lysyanskaya and Strobl. The dealer sends
+/// to each node a row and a column of a bivariate polynomial of degree `t`, nodes echo the
+/// points they share with each other and amplify with ready messages. Shares complete from the
+/// ready points, without any broadcast from the dealer. Both degrees of the polynomial are the
+/// fault threshold, so `t` stands for `f` here and `d` is ignored.
+#[derive(Default)]
+pub struct BivariateAvss {
+    /// Round of the sharing the node is set up for. The messages of a later one wait in
+    /// `early` for its `SETUP`, the ones of an earlier one are dropped with its session.
+    sharing: u32,
+    early: Vec<(Option<u16>, BivariateMessage)>,
+    session: Session,
+    /// The share of the node and the round of its sharing.
+    share: Option<(u32, Share)>,
+    /// Revealed shares, by reconstruction of the node.
+    reconstruct_shares: HashMap<u32, Points>,
+    /// Whether the revealed shares are being decoded, the ones arriving meanwhile are decoded
+    /// once it is over.
+    decoding: bool,
+    /// The reconstruction and the number of shares of the last decoding.
+    attempt: (u32, usize),
+}
+
+impl BivariateAvss {
+    pub const NAME: &'static str = "bivariate_avss";
+
+    pub fn build() -> Box<dyn Protocol> {
+        Box::new(BivariateAvss::default())
+    }
+
+    fn points_message(
+        code: BivariateCode,
+        round: u32,
+        index: u16,
+        points: (Share, Share),
+    ) -> BivariateMessage {
+        match code {
+            BivariateCode::ECHO => BivariateMessage::Echo {
+                round,
+                index,
+                points,
+            },
+            _ => BivariateMessage::Ready {
+                round,
+                index,
+                points,
+            },
+        }
+    }
+
+    /// Handles a message of the sharing `round`, kept until the `SETUP` of a later sharing and
+    /// dropped for an earlier one. The row and the column must come from the dealer and the
+    /// points from the node they are the points of.
+    fn sharing_message(&mut self, node: &mut Node, from: Option<u16>, msg: BivariateMessage) {
+        match msg.round().cmp(&self.sharing) {
+            Ordering::Greater => return self.early.push((from, msg)),
+            Ordering::Less => return,
+            Ordering::Equal => (),
+        }
+        self.session.timer.get_or_insert_with(Instant::now);
+        match msg {
+            BivariateMessage::Send { row, column, .. } if sent_by(from, node.dealer) => {
+                self.send_receiv(node, row, column)
+            },
+            BivariateMessage::Echo { index, points, .. } if sent_by(from, index) => {
+                self.points_receiv(node, BivariateCode::ECHO, index, points)
+            },
+            BivariateMessage::Ready { index, points, .. } if sent_by(from, index) => {
+                self.points_receiv(node, BivariateCode::READY, index, points)
+            },
+            _ => eprintln!(
+                "Node {}: message sent by {from:?} in the name of another",
+                node.index
+            ),
+        }
+    }
+
+    /// Computes for every node `j` the points `φ(j, i)` and `φ(i, j)`, which are on the row and
+    /// on the column of `j`, or random points when the node acts randomly. They are sent once
+    /// computed, see `points_computed`.
+    fn send_points(
+        node: &mut Node,
+        code: BivariateCode,
+        round: u32,
+        (row, column): CrossLines,
+        start: Option<Instant>,
+    ) {
+        let byz_comp = node.act();
+        if byz_comp == ByzComp::Sleeper {
+            return;
+        }
+        let n = node.n;
+        node.compute(&[(node.costs.eval, 2 * n as usize)], move || {
+            let points = (0..n)
+                .map(|j| match byz_comp {
+                    ByzComp::Random => (Share::random(), Share::random()),
+                    _ => (column.eval(j), row.eval(j)),
+                })
+                .collect();
+            Done::Points {
+                code,
+                round,
+                start,
+                points,
+            }
+        });
+    }
+
+    fn points_computed(
+        node: &mut Node,
+        code: BivariateCode,
+        round: u32,
+        start: Option<Instant>,
+        points: Vec<(Share, Share)>,
+    ) {
+        if let Some(start) = start {
+            node.result
+                .set(TypeResultField::FirstReceiv, start.elapsed().as_millis());
+        }
+        for (ext_node, points) in node.network.iter().zip(points) {
+            let msg = Self::points_message(code, round, node.index, points);
+            node.send(ext_node.addr(), &protocol_message(Self::NAME, &msg));
+        }
+    }
+
+    fn send_receiv(&mut self, node: &mut Node, row: Polynomial, column: Polynomial) {
+        let start = Instant::now();
+        let (i, degree) = (node.index, node.f as usize);
+        if row.degree() > degree || column.degree() > degree || row.eval(i) != column.eval(i) {
+            println!("Node {i}: I received invalid polynomials.");
+            return;
+        }
+        let s = &mut self.session;
+        if s.done || s.row.is_some() {
+            return;
+        }
+        s.row = Some(row.clone());
+        s.column = Some(column.clone());
+        let lines = (row, column);
+        Self::send_points(node, BivariateCode::ECHO, self.sharing, lines, Some(start));
+        self.progress(node)
+    }
+
+    fn points_receiv(
+        &mut self,
+        node: &mut Node,
+        code: BivariateCode,
+        j: u16,
+        points: (Share, Share),
+    ) {
+        if j < node.n && !self.session.done && self.session.receive(code, j, points) {
+            self.progress(node)
+        }
+    }
+
+    /// Sends the ready points once enough echoes or readies arrived, and outputs the share of the
+    /// node once `2t + 1` readies arrived. The row and the column are decoded from the points
+    /// off the loop when the dealer did not send them.
+    fn progress(&mut self, node: &mut Node) {
+        let (n, t, round) = (node.n as usize, node.f as usize, self.sharing);
+        let s = &mut self.session;
+        if s.done {
+            return;
+        }
+        if let Some(points) = s.to_complete(t) {
+            if !s.recovering && points.0.len() != s.tried {
+                (s.recovering, s.tried) = (true, points.0.len());
+                node.compute(&[(node.costs.decode, 2)], move || Done::Recovered {
+                    round,
+                    lines: Session::recover(points, t, t),
+                });
+            }
+            return;
+        }
+        if s.ready_due(n, t) {
+            s.ready_sent = true;
+            let lines = (s.row.clone().unwrap(), s.column.clone().unwrap());
+            Self::send_points(node, BivariateCode::READY, round, lines, None);
+        }
+        if s.complete(t) {
+            s.done = true;
+            s.echoes.clear();
+            s.readies.clear();
+            let share = s.row.take().unwrap().secret();
+            let timer = s.timer.unwrap_or_else(Instant::now);
+            self.share = Some((round, share));
+            node.save(Self::NAME, &self.share);
+            self.reconstruct_shares.clear();
+            node.result
+                .set(TypeResultField::Verify, timer.elapsed().as_millis());
+            node.output(Step::Sharing, ErrorCode::OK)
+        }
+    }
+
+    fn recovered(&mut self, node: &mut Node, round: u32, lines: Option<CrossLines>) {
+        if round != self.sharing {
+            return;
+        }
+        let s = &mut self.session;
+        s.recovering = false;
+        if let Some((row, column)) = lines.filter(|_| s.row.is_none() && !s.done) {
+            s.row = Some(row);
+            s.column = Some(column);
+        }
+        // Recovered or not, the points that arrived during the decoding.
+        self.progress(node)
+    }
+
+    fn new_share(
+        &mut self,
+        node: &mut Node,
+        round: u32,
+        reconstruction: u32,
+        j: u16,
+        share: Share,
+    ) {
+        if self.share.as_ref().map(|(round, _)| *round) != Some(round)
+            || reconstruction < node.reconstruction
+        {
+            return;
+        }
+        let shares = self.reconstruct_shares.entry(reconstruction).or_default();
+        if shares.iter().any(|(i, _)| *i == j) {
+            return;
+        }
+        shares.push((j, share));
+        self.decode(node)
+    }
+
+    /// Decodes the shares of the current reconstruction off the loop, unless a decoding is
+    /// running or they did not change since the last one.
+    fn decode(&mut self, node: &mut Node) {
+        let reconstruction = node.reconstruction;
+        let Some(shares) = self.reconstruct_shares.get(&reconstruction) else {
+            return;
+        };
+        let attempt = (reconstruction, shares.len());
+        if self.decoding || node.im_done || self.attempt == attempt {
+            return;
+        }
+        let t = node.f as usize;
+        if !Polynomial::decodable(shares.len(), t, t) {
+            return;
+        }
+        (self.decoding, self.attempt) = (true, attempt);
+        let shares = shares.clone();
+        node.compute(&[(node.costs.decode, 1)], move || Done::Decoded {
+            reconstruction,
+            decoded: Polynomial::decode(&shares, t, t),
+        });
+    }
+
+    fn decoded(
+        &mut self,
+        node: &mut Node,
+        reconstruction: u32,
+        decoded: Option<(Polynomial, Vec<u16>)>,
+    ) {
+        self.decoding = false;
+        match decoded {
+            Some((poly, wrong)) if !node.im_done && reconstruction == node.reconstruction => {
+                node.decoded(&poly.secret(), &wrong);
+                node.output(Step::Reconstruct, ErrorCode::OK)
+            },
+            // The shares that arrived during the decoding.
+            _ => self.decode(node),
+        }
+    }
+}
+
+impl Protocol for BivariateAvss {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    /// The session of the previous sharing is dropped, the messages of the new one that
+    /// arrived before the `SETUP` are handled.
+    fn setup(&mut self, node: &mut Node) {
+        self.sharing = node.round;
+        self.session = Session::default();
+        for (from, msg) in std::mem::take(&mut self.early) {
+            self.sharing_message(node, from, msg);
+        }
+    }
+
+    fn deal(&mut self, node: &mut Node, secret: u128) {
+        let start = Instant::now();
+        node.timer = Instant::now();
+        let (t, n, round) = (node.f, node.n, self.sharing);
+        let phi = BivariatePolynomial::rand(t as usize, secret);
+        node.compute(&[(node.costs.eval, 2 * n as usize)], move || {
+            let messages = (0..n)
+                .map(|i| BivariateMessage::Send {
+                    round,
+                    row: phi.row(i),
+                    column: phi.column(i),
+                })
+                .collect();
+            Done::Dealt { start, messages }
+        });
+    }
+
+    fn new_message(&mut self, node: &mut Node, from: Option<u16>, bytes: Bytes<'_>) {
+        let msg = match BivariateMessage::from_bytes(bytes) {
+            Ok(msg) => msg,
+            Err(e) => return eprintln!("Invalid {} message: {e}", Self::NAME),
+        };
+        match msg {
+            BivariateMessage::NewShare { index, .. }
+                if index >= node.n || !sent_by(from, index) =>
+            {
+                eprintln!("Node {}: share of {index} sent by {from:?}", node.index)
+            },
+            BivariateMessage::NewShare {
+                round,
+                reconstruction,
+                index,
+                share,
+            } => self.new_share(node, round, reconstruction, index, share),
+            msg => self.sharing_message(node, from, msg),
+        }
+    }
+
+    fn reconstruct(&mut self, node: &mut Node) {
+        let reconstruction = node.reconstruction;
+        self.reconstruct_shares.retain(|r, _| *r >= reconstruction);
+        let Some((round, share)) = self.share.clone() else {
+            return;
+        };
+        // The shares that arrived before the node started the reconstruction.
+        self.decode(node);
+        let byz_comp = node.act();
+        if byz_comp == ByzComp::Sleeper {
+            return;
+        }
+        let (index, share) = revealed_share(byz_comp, node.index, node.n, &share);
+        let msg = BivariateMessage::NewShare {
+            round,
+            reconstruction,
+            index,
+            share,
+        };
+        node.broadcast(&protocol_message(Self::NAME, &msg));
+    }
+
+    fn computed(&mut self, node: &mut Node, result: Computed) {
+        let Ok(done) = result.downcast::<Done>() else {
+            return;
+        };
+        match *done {
+            Done::Dealt { start, messages } => {
+                node.result.set(
+                    TypeResultField::MessagesComputing,
+                    start.elapsed().as_millis(),
+                );
+                for (ext_node, msg) in node.network.iter().zip(messages) {
+                    node.send(ext_node.addr(), &protocol_message(Self::NAME, &msg));
+                }
+                node.result
+                    .set(TypeResultField::Dealing, start.elapsed().as_millis());
+            },
+            Done::Points {
+                code,
+                round,
+                start,
+                points,
+            } => Self::points_computed(node, code, round, start, points),
+            Done::Recovered { round, lines } => self.recovered(node, round, lines),
+            Done::Decoded {
+                reconstruction,
+                decoded,
+            } => self.decoded(node, reconstruction, decoded),
+        }
+    }
+
+    /// The share of the last sharing of the node, kept outside the `ShareSet` as it has no
+    /// proof.
+    fn restore(&mut self, node: &mut Node) {
+        self.share = node.load::<Option<(u32, Share)>>(Self::NAME).flatten();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrong_points_do_not_block_completion() {
+        let t = 2;
+        let phi = BivariatePolynomial::rand(t, 42);
+        let (row, column) = (phi.row(0), phi.column(0));
+        let mut session = Session::default();
+        for j in 0..2 * t as u16 + 2 {
+            session.readies.insert(j, (row.eval(j), column.eval(j)));
+        }
+        session
+            .readies
+            .insert(1, (Share::random(), Share::random()));
+        let (a, b) = Session::recover(session.to_complete(t).unwrap(), t, t).unwrap();
+        assert!((0..10).all(|j| a.eval(j) == row.eval(j) && b.eval(j) == column.eval(j)));
+    }
+
+    /// A session of the node 0 knowing its row and its column, with the points of the nodes
+    /// `0..n` on them.
+    fn known_lines(t: usize, n: u16) -> (Session, Vec<(Share, Share)>) {
+        let phi = BivariatePolynomial::rand(t, 42);
+        let session = Session {
+            row: Some(phi.row(0)),
+            column: Some(phi.column(0)),
+            ..Session::default()
+        };
+        let points = (0..n)
+            .map(|j| (phi.row(0).eval(j), phi.column(0).eval(j)))
+            .collect();
+        (session, points)
+    }
+
+    #[test]
+    fn ready_needs_enough_agreeing_echoes() {
+        // n = 7, t = 2: the ready points go after ⌈(n + t + 1) / 2⌉ = 5 echoes.
+        let (t, n) = (2, 7);
+        let (mut session, points) = known_lines(t, n as u16);
+        for (j, p) in points.iter().enumerate().take(4) {
+            assert!(session.receive(BivariateCode::ECHO, j as u16, p.clone()));
+        }
+        let wrong = (Share::random(), Share::random());
+        assert!(session.receive(BivariateCode::ECHO, 4, wrong));
+        assert!(!session.ready_due(n, t));
+        // The node 4 in its own name, its first points were sent by another node.
+        assert!(session.receive(BivariateCode::ECHO, 4, points[4].clone()));
+        assert!(session.ready_due(n, t));
+        assert!(!session.receive(BivariateCode::ECHO, 4, (Share::random(), Share::random())));
+    }
+
+    #[test]
+    fn ready_and_output_need_enough_agreeing_readies() {
+        let (t, n) = (2, 7);
+        let (mut session, points) = known_lines(t, n as u16);
+        let wrong = (Share::random(), Share::random());
+        assert!(session.receive(BivariateCode::READY, 0, wrong));
+        assert!(session.receive(BivariateCode::READY, 1, points[1].clone()));
+        assert!(session.receive(BivariateCode::READY, 2, points[2].clone()));
+        assert!(!session.ready_due(n, t));
+        assert!(session.receive(BivariateCode::READY, 3, points[3].clone()));
+        assert!(session.ready_due(n, t));
+        session.ready_sent = true;
+        assert!(!session.ready_due(n, t));
+        assert!(session.receive(BivariateCode::READY, 4, points[4].clone()));
+        assert!(!session.complete(t));
+        assert!(session.receive(BivariateCode::READY, 5, points[5].clone()));
+        assert!(session.complete(t));
+    }
+
+    #[test]
+    fn points_count_only_once_the_lines_are_known() {
+        let (t, n) = (2, 7);
+        let (known, points) = known_lines(t, n as u16);
+        let mut session = Session::default();
+        for (j, p) in points.iter().enumerate() {
+            session.receive(BivariateCode::ECHO, j as u16, p.clone());
+            session.receive(BivariateCode::READY, j as u16, p.clone());
+        }
+        assert_eq!(session.agreeing(&session.echoes), 0);
+        assert!(!session.ready_due(n, t));
+        session.row = known.row;
+        session.column = known.column;
+        assert!(session.ready_due(n, t));
+        // 2t points can't correct t wrong ones.
+        session.row = None;
+        session.echoes.retain(|j, _| *j < 2 * t as u16);
+        session.readies.clear();
+        assert!(session.to_complete(t).is_none());
+    }
+
+    #[tokio::test]
+    async fn messages_in_the_name_of_another_are_dropped() {
+        let (mut node, _computed) = Node::new(
+            String::new(),
+            String::new(),
+            Arc::new(MemoryNetwork::default()),
+        );
+        (node.n, node.f, node.round, node.dealer) = (4, 1, 3, 0);
+        let mut avss = BivariateAvss::default();
+        avss.setup(&mut node);
+        let phi = BivariatePolynomial::rand(1, 42);
+        let echo = |round, index| BivariateMessage::Echo {
+            round,
+            index,
+            points: (phi.row(index).eval(0), phi.column(index).eval(0)),
+        };
+        let send = BivariateMessage::Send {
+            round: 3,
+            row: phi.row(0),
+            column: phi.column(0),
+        };
+        for (from, msg) in [
+            (Some(1), send),
+            (Some(2), echo(3, 1)),
+            (None, echo(3, 4)),
+            (Some(1), echo(2, 1)),
+        ] {
+            avss.new_message(&mut node, from, &msg.to_bytes());
+        }
+        assert!(avss.session.row.is_none() && avss.session.echoes.is_empty());
+        avss.new_message(&mut node, Some(1), &echo(3, 1).to_bytes());
+        avss.new_message(&mut node, Some(2), &echo(4, 2).to_bytes());
+        assert_eq!(avss.session.echoes.len(), 1);
+        assert_eq!(avss.early.len(), 1);
+    }
+
+    #[test]
+    fn invalid_messages_are_rejected() {
+        let phi = BivariatePolynomial::rand(2, 42);
+        let messages = [
+            BivariateMessage::Send {
+                round: 9,
+                row: phi.row(0),
+                column: phi.column(0),
+            },
+            BivariateMessage::Echo {
+                round: 9,
+                index: 1,
+                points: (phi.row(1).eval(0), phi.column(1).eval(0)),
+            },
+            BivariateMessage::Ready {
+                round: 9,
+                index: 1,
+                points: (Share::random(), Share::random()),
+            },
+            BivariateMessage::NewShare {
+                round: 9,
+                reconstruction: 3,
+                index: 2,
+                share: phi.row(2).secret(),
+            },
+        ];
+        for msg in messages {
+            let bytes = msg.to_bytes();
+            assert!(BivariateMessage::from_bytes(&bytes).is_ok());
+            for len in 0..bytes.len() {
+                assert!(BivariateMessage::from_bytes(&bytes[..len]).is_err());
+            }
+            let mut longer = bytes.clone();
+            longer.push(0);
+            assert!(BivariateMessage::from_bytes(&longer).is_err());
+        }
+        // A row announcing more coefficients than the message holds.
+        let mut bytes = BivariateMessage::Send {
+            round: 9,
+            row: phi.row(0),
+            column: phi.column(0),
+        }
+        .to_bytes();
+        bytes[5..9].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(BivariateMessage::from_bytes(&bytes).is_err());
+    }
+}
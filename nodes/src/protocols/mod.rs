@@ -1 +1,105 @@
 pub mod avss_simpl;
+pub mod bivariate_avss;
+
+use crate::*;
+use avss_simpl::AvssSimpl;
+use bivariate_avss::BivariateAvss;
+
+pub type ProtocolBuilder = fn() -> Box<dyn Protocol>;
+
+/// Every sharing protocol a node is able to run, keyed by the name used in the config. The
+/// first one is run by default.
+pub static PROTOCOLS: [(&str, ProtocolBuilder); 2] = [
+    (AvssSimpl::NAME, AvssSimpl::build),
+    (BivariateAvss::NAME, BivariateAvss::build),
+];
+
+/// The names of `PROTOCOLS`, checked against the config.
+pub fn protocol_names() -> Vec<&'static str> {
+    PROTOCOLS.iter().map(|(name, _)| *name).collect()
+}
+
+/// Index of the protocol in `PROTOCOLS`, `None` when it is not registered.
+pub fn protocol_id(name: &str) -> Option<u8> {
+    PROTOCOLS
+        .iter()
+        .position(|(protocol, _)| *protocol == name)
+        .map(|id| id as u8)
+}
+
+/// Wraps a message of `protocol`, one of `PROTOCOLS`, written with its code first.
+pub fn protocol_message(protocol: &'static str, msg: &impl Wire) -> NodeMessage {
+    NodeMessage::Protocol {
+        protocol: protocol_id(protocol).expect("The protocols send as registered"),
+        bytes: msg.to_bytes(),
+    }
+}
+
+/// The index and the share a node reveals for the reconstruction, tampered with when it is
+/// byzantine.
+pub fn revealed_share(byz_comp: ByzComp, index: u16, n: u16, share: &Share) -> (u16, Share) {
+    match byz_comp {
+        ByzComp::WrongShares => (index, share.tampered()),
+        ByzComp::ForeignShares => ((index + 1) % n, share.clone()),
+        ByzComp::Random => (index, Share::random()),
+        _ => (index, share.clone()),
+    }
+}
+
+/// Whether a message claiming to be sent by the node `index` comes from it. Only the frames of
+/// authenticated streams have a sender, the nodes of a single process are not checked.
+pub fn sent_by(from: Option<u16>, index: u16) -> bool {
+    from.is_none_or(|from| from == index)
+}
+
+pub fn protocol_from_name(name: &str) -> Option<Box<dyn Protocol>> {
+    PROTOCOLS
+        .iter()
+        .find(|(protocol, _)| *protocol == name)
+        .map(|(_, build)| build())
+}
+
+/// A sharing/reconstruction protocol, run by the event loop of the node. The node handles the
+/// setup and the control messages of the interface, every `NodeMessage::Protocol` tagged with
+/// the id of the protocol selected during the setup is forwarded to it. The messages of the
+/// other protocols, sent before the node switched protocols or after, are dropped. The handlers
+/// only change the state of the node and of the protocol, the computations run off the loop
+/// with `Node::compute` and their result comes back to `computed`.
+pub trait Protocol: Send {
+    fn name(&self) -> &'static str;
+
+    /// Called once the node is set up for a new sharing.
+    fn setup(&mut self, node: &mut Node);
+
+    /// Called on the dealer when the interface asks to share `secret`.
+    fn deal(&mut self, node: &mut Node, secret: u128);
+
+    /// Called for every protocol message, `bytes` starts with its code. `from` is the index of
+    /// its sender when the certificate of its stream authenticated it, see `sent_by`.
+    fn new_message(&mut self, node: &mut Node, from: Option<u16>, bytes: Bytes<'_>);
+
+    /// Called once the node switched to the reconstruction step.
+    fn reconstruct(&mut self, node: &mut Node);
+
+    /// Called with the result of a computation the protocol started. The results of the
+    /// previous protocol, when the node switched protocols meanwhile, are dropped.
+    fn computed(&mut self, node: &mut Node, result: Computed);
+
+    /// Called when the node restarted from its state directory, once it is set up for its
+    /// last sharing. The protocol takes back what it saved with `Node::save`.
+    fn restore(&mut self, _node: &mut Node) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn protocols_are_built_under_their_name() {
+        for (id, name) in protocol_names().into_iter().enumerate() {
+            assert_eq!(protocol_from_name(name).unwrap().name(), name);
+            assert_eq!(protocol_id(name), Some(id as u8));
+        }
+        assert!(protocol_from_name("unknown").is_none() && protocol_id("unknown").is_none());
+    }
+}
@@ -0,0 +1,128 @@
+use crate::*;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Followed by a directory, the node keeps its state there and takes it back when it restarts,
+/// see `Store`.
+pub static STATE_FLAG: &str = "--state=";
+
+/// File of the `Identity` of the node.
+pub const IDENTITY: &str = "identity";
+/// File of the `Roster` of the last sharing.
+pub const ROSTER: &str = "roster";
+/// File of the shares the node output with their commitment and the round of their sharing,
+/// see `ShareSet`.
+pub const SHARES: &str = "shares";
+
+wire_struct!(
+    /// What the other nodes and the interface know the node by: the address it advertises and
+    /// its key pair in PKCS#8, from which its certificate is made.
+    struct Identity {
+        addr: String,
+        key: Vec<u8>,
+    }
+);
+
+wire_struct!(
+    /// The network of the last sharing of the node.
+    struct Roster {
+        /// Its `SETUP`, with the addresses and the certificates of the whole network.
+        setup: Setup,
+        /// Keys of the nodes of the network, empty for the ones the node did not get.
+        keys: Vec<PublicKey>,
+    }
+);
+
+impl Wire for ShareSet {
+    fn write(&self, buf: &mut Vec<u8>) {
+        self.comm.write(buf);
+        let set = self
+            .set
+            .iter()
+            .map(|(i, (share, proof))| (*i, share.clone(), proof.clone()));
+        let mut set = set.collect::<Vec<_>>();
+        set.sort_by_key(|(i, _, _)| *i);
+        set.write(buf);
+    }
+
+    fn read(bytes: &mut Bytes<'_>) -> Result<Self, &'static str> {
+        let comm = Option::<Commitment>::read(bytes)?;
+        let set = Vec::<(u16, Share, Proof)>::read(bytes)?;
+        Ok(ShareSet {
+            comm,
+            set: set
+                .into_iter()
+                .map(|(i, share, proof)| (i, (share, proof)))
+                .collect(),
+        })
+    }
+}
+
+/// The state directory of a node. A file is written next to the previous one then renamed over
+/// it, a crash leaves either of them whole.
+pub struct Store {
+    dir: PathBuf,
+}
+
+impl Store {
+    pub fn open(dir: impl Into<PathBuf>) -> Store {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir).expect("Failed to create the state directory");
+        Store { dir }
+    }
+
+    /// Failures are only reported, the node goes on with the state it has in memory.
+    pub fn save(&self, name: &str, value: &impl Wire) {
+        let path = self.dir.join(name);
+        let temp = self.dir.join(format!("{name}.tmp"));
+        let write = || -> std::io::Result<()> {
+            let mut file = std::fs::File::create(&temp)?;
+            file.write_all(&value.to_bytes())?;
+            file.sync_all()?;
+            std::fs::rename(&temp, &path)?;
+            // The rename itself is durable once the directory is.
+            std::fs::File::open(&self.dir)?.sync_all()
+        };
+        if let Err(e) = write() {
+            eprintln!("Failed to save {}: {e}", path.display());
+        }
+    }
+
+    /// `None` when the file was never saved or is invalid.
+    pub fn load<T: Wire>(&self, name: &str) -> Option<T> {
+        let path = self.dir.join(name);
+        let bytes = std::fs::read(&path).ok()?;
+        T::from_bytes(&bytes)
+            .map_err(|e| eprintln!("Invalid {}: {e}", path.display()))
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_values_are_loaded_back() {
+        let dir = std::env::temp_dir().join(format!("store_{}", std::process::id()));
+        let store = Store::open(&dir);
+        assert!(store.load::<ShareSet>(SHARES).is_none());
+        let (comm, output) = compute_proof_and_shares(4, 2, 42);
+        let mut shares = ShareSet::new();
+        shares.set_comm(comm);
+        for (i, (proof, share)) in output.into_iter().enumerate().rev() {
+            shares.new_share(i as u16, share, proof);
+        }
+        store.save(SHARES, &shares);
+        let loaded = store.load::<ShareSet>(SHARES).unwrap();
+        assert_eq!(loaded.to_bytes(), shares.to_bytes());
+        assert!(loaded.get(3).0 == shares.get(3).0);
+        // A save overwrites the file, no temporary file is left.
+        store.save(SHARES, &ShareSet::new());
+        assert_eq!(store.load::<ShareSet>(SHARES).unwrap().len(), 0);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::write(dir.join(SHARES), [1, 2]).unwrap();
+        assert!(store.load::<ShareSet>(SHARES).is_none());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -104,6 +104,15 @@ impl Args {
         }
     }
 
+    /// Writes the trace of a simulation next to the results.
+    pub fn write_trace(&self, trace: &[String]) {
+        if !self.output.is_empty() {
+            let output = self.output.trim_end_matches(".json");
+            let path = format!("../configs/results/{output}.trace");
+            std::fs::write(path, trace.join("\n") + "\n").expect("Failed to write the trace");
+        }
+    }
+
     pub fn reset(&mut self) {
         self.current_arg_mut().reset()
     }
@@ -116,12 +125,93 @@ impl Args {
         self.get_fields().t()
     }
 
+    pub fn d(&self) -> u16 {
+        self.get_fields().d()
+    }
+
+    pub fn f(&self) -> u16 {
+        self.get_fields().f()
+    }
+
     pub fn nb_byz(&self) -> u16 {
         self.get_fields().get(TypeField::NbByz)
     }
 
+    pub fn check(&self) -> Result<(), &'static str> {
+        self.current_arg().check_fields()
+    }
+
+    /// Indices of the byzantine nodes, the dealer is the first honest one, or the first
+    /// byzantine one when `byz_comp` is a dealer behaviour.
+    pub fn byzantine_and_dealer(&self) -> (Vec<u16>, u16) {
+        let byzantine = self.current_arg().byzantine();
+        let dealer = if self.byz_comp().is_dealer() {
+            byzantine[0]
+        } else {
+            (0..self.n()).find(|i| !byzantine.contains(i)).unwrap()
+        };
+        (byzantine, dealer)
+    }
+
+    pub fn network(&self) -> &NetworkEmulation {
+        self.current_arg().network()
+    }
+
+    pub fn costs(&self) -> Costs {
+        self.current_arg().costs()
+    }
+
+    pub fn protocol(&self) -> &str {
+        self.current_arg().protocol()
+    }
+
+    pub fn decoding(&self) -> bool {
+        self.get_fields().get(TypeField::Decoding) != 0
+    }
+
     pub fn byz_comp(&self) -> ByzComp {
-        (self.get_fields().get(TypeField::ByzComp) as u8).into()
+        self.get_fields().byz_comp()
+    }
+
+    pub fn probas(&self) -> [u8; 3] {
+        self.get_fields().probas()
+    }
+
+    /// Whether messages open a new connection instead of using the stream of the peer.
+    pub fn connect_per_message(&self) -> bool {
+        self.get_fields().get(TypeField::ConnectPerMessage) != 0
+    }
+
+    /// Time in ms after which a run is ended without the missing outputs, 0 for none.
+    pub fn timeout(&self) -> u64 {
+        self.get_fields().get(TypeField::Timeout) as u64
+    }
+
+    pub fn nb_adaptive(&self) -> u16 {
+        self.get_fields().get(TypeField::NbAdaptive)
+    }
+
+    pub fn adaptive_step(&self) -> Step {
+        self.get_fields().adaptive_step()
+    }
+
+    /// Delay in ms between the start of `adaptive_step` and the corruption.
+    pub fn adaptive_delay(&self) -> u64 {
+        self.get_fields().get(TypeField::AdaptiveDelay) as u64
+    }
+
+    /// Number of nodes crashed in each run, the nodes already crashed included.
+    pub fn nb_crash(&self) -> u16 {
+        self.get_fields().get(TypeField::NbCrash)
+    }
+
+    /// Delay in ms between the start of the sharing and the crash of the nodes.
+    pub fn crash_delay(&self) -> u64 {
+        self.get_fields().get(TypeField::CrashDelay) as u64
+    }
+
+    pub fn victims(&self) -> u16 {
+        self.get_fields().victims()
     }
 
     pub fn hmt(&self, eval: Evaluation) -> usize {
@@ -144,10 +234,16 @@ impl Args {
     fn handle_setup(setup: &JsonMap, subarg: &mut SubArgs) -> Result<(), &'static str> {
         for (key, value) in setup {
             match value {
+                _ if key == "byz_placement" => {
+                    subarg.set_byz_placement(ByzPlacement::from_serde(value)?)
+                },
+                _ if key == "network" => subarg.set_network(NetworkEmulation::from_serde(value)?),
+                _ if key == "costs" => subarg.set_costs(Costs::from_serde(value)?),
                 JsonValue::Number(n) => subarg.set_field_from_str(key, n.as_u64().unwrap() as u16),
                 JsonValue::Array(arr) => {
                     subarg.set_variation_data(VariationData::from_serde_str(key, arr)?)
                 },
+                JsonValue::String(protocol) if key == "protocol" => subarg.set_protocol(protocol),
                 _ => return Err("Invalid arg for the setup"),
             }
         }
@@ -190,14 +286,16 @@ impl Args {
         Ok(())
     }
 
-    pub fn from_file(path: String) -> Result<Args, &'static str> {
+    /// `protocols` are the names of the protocols the nodes are able to run, the first one is
+    /// run by default.
+    pub fn from_file(path: String, protocols: &[&str]) -> Result<Args, &'static str> {
         let mut res = Args::default();
         let content = read_to_string(path).expect("Path invalid");
         let value: JsonValue = from_str(&content).expect("The given json file is invalid");
         let json_args = extract_serde_arr(&value);
         Self::handle_args(&mut res, &json_args[0])?;
         for sim in json_args.iter().skip(1).map(extract_serde_obj) {
-            let mut subarg = SubArgs::new();
+            let mut subarg = SubArgs::new(protocols[0]);
             for (key, val) in sim.iter() {
                 let val = extract_serde_obj(val);
                 (match key as &str {
@@ -207,6 +305,7 @@ impl Args {
                     _ => return Err("Invalid key"),
                 })(val, &mut subarg)?
             }
+            subarg.check(protocols)?;
             res.args.push(subarg);
         }
         Ok(res)
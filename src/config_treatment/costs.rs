@@ -0,0 +1,117 @@
+use super::include::*;
+use crate::wire_struct;
+
+/// Names of the operations of `Costs`, as given in the configuration.
+pub static COST_NAMES: [&str; 7] = [
+    "commit",
+    "prove",
+    "verify",
+    "sign",
+    "verify_sign",
+    "decode",
+    "eval",
+];
+
+wire_struct!(
+    /// Modeled durations of the operations of the nodes, in µs. A node spends them on top of
+    /// the real duration of the operation, which does not count on the virtual clock of a
+    /// simulation, so they are the only cost of the crypto there.
+    #[derive(Debug, Clone, Copy, Default, PartialEq)]
+    struct Costs {
+        /// Commitment to the polynomial of the dealer.
+        commit: u32,
+        /// Proof of one share.
+        prove: u32,
+        /// Verification of one share against the commitment.
+        verify: u32,
+        sign: u32,
+        verify_sign: u32,
+        /// Reed-Solomon decoding of a set of shares or points.
+        decode: u32,
+        /// Evaluation of one polynomial, or one row or column of a bivariate polynomial.
+        eval: u32,
+    }
+);
+
+impl Costs {
+    pub fn from_serde(value: &JsonValue) -> Result<Costs, &'static str> {
+        let obj = value
+            .as_object()
+            .ok_or("Invalid costs, expected an object")?;
+        let mut costs = Costs::default();
+        for (key, value) in obj {
+            let cost = value.as_u64().ok_or("Invalid number in the costs")? as u32;
+            match key as &str {
+                "commit" => costs.commit = cost,
+                "prove" => costs.prove = cost,
+                "verify" => costs.verify = cost,
+                "sign" => costs.sign = cost,
+                "verify_sign" => costs.verify_sign = cost,
+                "decode" => costs.decode = cost,
+                "eval" => costs.eval = cost,
+                _ => return Err("Invalid key for the costs"),
+            }
+        }
+        Ok(costs)
+    }
+
+    pub fn to_serde(self) -> JsonValue {
+        let costs = [
+            self.commit,
+            self.prove,
+            self.verify,
+            self.sign,
+            self.verify_sign,
+            self.decode,
+            self.eval,
+        ];
+        let obj = COST_NAMES
+            .iter()
+            .zip(costs)
+            .map(|(name, cost)| (name.to_string(), cost.into()))
+            .collect::<JsonMap>();
+        JsonValue::Object(obj)
+    }
+}
+
+/// Spends the modeled duration of `times` operations costing `cost` µs each.
+pub async fn spend(cost: u32, times: usize) {
+    if cost > 0 && times > 0 {
+        let micros = cost as u64 * times as u64;
+        tokio::time::sleep(std::time::Duration::from_micros(micros)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Instant, Wire};
+    use serde_json::json;
+
+    #[test]
+    fn serde_and_wire_round_trip() {
+        let costs = Costs::from_serde(&json!({"verify": 900, "eval": 3})).unwrap();
+        assert_eq!(
+            costs,
+            Costs {
+                verify: 900,
+                eval: 3,
+                ..Costs::default()
+            }
+        );
+        assert_eq!(Costs::from_serde(&costs.to_serde()), Ok(costs));
+        assert_eq!(Costs::from_bytes(&costs.to_bytes()), Ok(costs));
+        assert!(Costs::from_serde(&json!({"verfy": 900})).is_err());
+        assert!(Costs::from_serde(&json!({"verify": -1})).is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn costs_are_spent_on_the_clock() {
+        let start = Instant::now();
+        spend(1500, 4).await;
+        assert_eq!(start.elapsed(), std::time::Duration::from_millis(6));
+        spend(0, 4).await;
+        spend(1500, 0).await;
+        assert_eq!(start.elapsed(), std::time::Duration::from_millis(6));
+    }
+}
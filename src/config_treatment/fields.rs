@@ -4,14 +4,54 @@ as_number!(
     usize,
     enum TypeField {
         N,
-        TDenom,
+        TPercent,
         NbByz,
         ByzComp,
+        Decoding,
+        D,
+        F,
         T,
+        TRatio,
+        Victims,
+        PHonnest,
+        PRandom,
+        PSleeper,
+        NbAdaptive,
+        AdaptiveStep,
+        AdaptiveDelay,
+        NbCrash,
+        CrashDelay,
+        ConnectPerMessage,
+        Timeout,
     }
 );
 
-pub static STATIC_TYPE_FIELD: [&str; 4] = ["n", "t", "nb_byz", "byz_comp"];
+pub static STATIC_TYPE_FIELD: [&str; 20] = [
+    "n",
+    "t_percent",
+    "nb_byz",
+    "byz_comp",
+    "decoding",
+    "d",
+    "f",
+    "t",
+    "t_ratio",
+    "victims",
+    "p_honnest",
+    "p_random",
+    "p_sleeper",
+    "nb_adaptive",
+    "adaptive_step",
+    "adaptive_delay",
+    "nb_crash",
+    "crash_delay",
+    "connect_per_message",
+    "timeout",
+];
+
+/// The keys giving the threshold, at most one of them is set.
+pub static THRESHOLD_TYPE_FIELD: [TypeField; 3] =
+    [TypeField::T, TypeField::TRatio, TypeField::TPercent];
 
 impl Display for TypeField {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtErr> {
@@ -33,13 +73,16 @@ impl From<&str> for TypeField {
     }
 }
 
+#[derive(Clone)]
 pub struct Fields {
     fields: Vec<u16>,
 }
 
 impl Fields {
     pub fn new() -> Self {
-        Fields { fields: vec![0; 4] }
+        Fields {
+            fields: vec![0; STATIC_TYPE_FIELD.len()],
+        }
     }
 
     pub fn get(&self, field: TypeField) -> u16 {
@@ -54,15 +97,289 @@ impl Fields {
         self.get(TypeField::N)
     }
 
+    /// The threshold, given as is by `t`, as `(n-1)/t_ratio` or as `t_percent`% of `n - 1`.
     pub fn t(&self) -> u16 {
-        ((self.n() - 1) as f32 * (self.get(TypeField::TDenom) as f32 / 100.0)) as u16
+        let n = self.n().saturating_sub(1);
+        match (
+            self.get(TypeField::T),
+            self.get(TypeField::TRatio),
+            self.get(TypeField::TPercent),
+        ) {
+            (0, 0, percent) => (n as f32 * (percent as f32 / 100.0)) as u16,
+            (0, ratio, _) => n / ratio,
+            (t, _, _) => t,
+        }
+    }
+
+    /// Number of faults tolerated, `t` when `f` is not given.
+    pub fn f(&self) -> u16 {
+        match self.get(TypeField::F) {
+            0 => self.t(),
+            f => f,
+        }
+    }
+
+    /// Degree of the sharing polynomial, `2f` when `d` is not given.
+    pub fn d(&self) -> u16 {
+        match self.get(TypeField::D) {
+            0 => self.f().saturating_mul(2),
+            d => d,
+        }
+    }
+
+    pub fn byz_comp(&self) -> ByzComp {
+        (self.get(TypeField::ByzComp) as u8).into()
+    }
+
+    /// Number of nodes attacked by a byzantine dealer, the last ones, `f` when not given.
+    pub fn victims(&self) -> u16 {
+        match self.get(TypeField::Victims) {
+            0 => self.f(),
+            victims => victims,
+        }
+    }
+
+    /// Percentages of the actions of a `ByzComp::Mixed` node: honnest, random and sleeper.
+    pub fn probas(&self) -> [u8; 3] {
+        [TypeField::PHonnest, TypeField::PRandom, TypeField::PSleeper].map(|f| self.get(f) as u8)
+    }
+
+    /// The step during which the `nb_adaptive` last byzantine nodes are corrupted.
+    pub fn adaptive_step(&self) -> Step {
+        match self.get(TypeField::AdaptiveStep) {
+            0 => Step::Sharing,
+            _ => Step::Reconstruct,
+        }
+    }
+
+    /// The dealer completes once `d + 1` nodes acknowledged, which must not exceed the `n - f`
+    /// honest ones that did not crash, and the at most `n - d - 1` shares it broadcasts must
+    /// keep the `f` corrupted nodes from learning the secret. The bounds are computed on u32,
+    /// the fields are u16.
+    pub fn check(&self) -> Result<(), &'static str> {
+        let given = THRESHOLD_TYPE_FIELD
+            .iter()
+            .filter(|field| self.get(**field) != 0)
+            .count();
+        if given > 1 {
+            return Err("only one of t, t_ratio and t_percent can be given");
+        }
+        let field = |field| self.get(field) as u32;
+        let (n, t, d, f) = (
+            self.n() as u32,
+            self.t() as u32,
+            self.d() as u32,
+            self.f() as u32,
+        );
+        if n < 3 * t + 1 {
+            return Err("n must be at least 3t + 1");
+        }
+        if n < 3 * f + 1 {
+            return Err("n must be at least 3f + 1");
+        }
+        if d < 2 * f {
+            return Err("d must be at least 2f");
+        }
+        if d + f >= n {
+            return Err("d must be at most n - f - 1");
+        }
+        if self.byz_comp() == ByzComp::OverDegree {
+            return Err(
+                "an over-degree dealer is out of scope, nothing bounds the committed degree",
+            );
+        }
+        if self.byz_comp().is_dealer() && field(TypeField::NbByz) == 0 {
+            return Err("a byzantine dealer needs nb_byz to be at least 1");
+        }
+        if !self.byz_comp().is_dealer() && field(TypeField::NbByz) >= n {
+            return Err("an honest dealer needs nb_byz to be below n");
+        }
+        if field(TypeField::Decoding) != 0 && d + 2 * f >= n {
+            return Err("decoding needs d + 2f + 1 nodes to correct f wrong shares");
+        }
+        let byz_comp = self.byz_comp();
+        if byz_comp != ByzComp::Honnest && !byz_comp.is_dealer() && field(TypeField::NbByz) + d >= n
+        {
+            return Err("the byzantine nodes must leave d + 1 honest nodes");
+        }
+        if byz_comp == ByzComp::Mixed && self.probas().iter().map(|p| *p as u32).sum::<u32>() != 100
+        {
+            return Err("p_honnest, p_random and p_sleeper must sum to 100");
+        }
+        let nb_adaptive = field(TypeField::NbAdaptive);
+        if nb_adaptive > field(TypeField::NbByz) {
+            return Err("nb_adaptive must be at most nb_byz");
+        }
+        if nb_adaptive > 0 && (byz_comp == ByzComp::Honnest || byz_comp.is_dealer()) {
+            return Err("adaptive corruption needs a byzantine receiver behaviour");
+        }
+        if field(TypeField::AdaptiveStep) > 1 {
+            return Err("adaptive_step must be 0 (sharing) or 1 (reconstruction)");
+        }
+        if self.adaptive_step() == Step::Reconstruct && self.get(TypeField::AdaptiveDelay) != 0 {
+            return Err("adaptive_delay must be 0 when corrupting during the reconstruction");
+        }
+        let nb_crash = field(TypeField::NbCrash);
+        if nb_crash > 0 && field(TypeField::NbByz) + nb_crash > f {
+            return Err("nb_byz + nb_crash must be at most f");
+        }
+        if self.victims() as u32 + 1 > n {
+            return Err("victims must be at most n - 1");
+        }
+        if self.byz_comp() == ByzComp::InvalidShares && self.victims() as u32 + d >= n {
+            return Err("the invalid shares must leave d + 1 nodes to acknowledge");
+        }
+        Ok(())
     }
 }
 
 impl Default for Fields {
     fn default() -> Self {
         Fields {
-            fields: vec![61, 20, 0, ByzComp::Sleeper.to_u16()],
+            fields: vec![
+                61,
+                20,
+                0,
+                ByzComp::Sleeper.to_u16(),
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(values: &[(TypeField, u16)]) -> Fields {
+        let mut fields = Fields::new();
+        fields.set(TypeField::ByzComp, ByzComp::Honnest.to_u16());
+        for (field, val) in values {
+            fields.set(*field, *val);
+        }
+        fields
+    }
+
+    #[test]
+    fn threshold_keys() {
+        let n = (TypeField::N, 31);
+        assert_eq!(fields(&[n, (TypeField::T, 7)]).t(), 7);
+        assert_eq!(fields(&[n, (TypeField::TRatio, 3)]).t(), 10);
+        assert_eq!(fields(&[n, (TypeField::TPercent, 20)]).t(), 6);
+        assert_eq!(fields(&[n]).t(), 0);
+    }
+
+    #[test]
+    fn d_and_f_default_to_the_threshold() {
+        let given = fields(&[(TypeField::N, 31), (TypeField::T, 5)]);
+        assert_eq!((given.f(), given.d()), (5, 10));
+        let given = fields(&[(TypeField::N, 31), (TypeField::T, 5), (TypeField::F, 4)]);
+        assert_eq!((given.f(), given.d()), (4, 8));
+        let given = fields(&[(TypeField::N, 31), (TypeField::T, 5), (TypeField::D, 20)]);
+        assert_eq!((given.f(), given.d()), (5, 20));
+    }
+
+    #[test]
+    fn check_accepts_the_bounds() {
+        let n = (TypeField::N, 31);
+        assert_eq!(fields(&[n, (TypeField::T, 10)]).check(), Ok(()));
+        assert_eq!(
+            fields(&[n, (TypeField::T, 10), (TypeField::D, 20)]).check(),
+            Ok(())
+        );
+        assert_eq!(fields(&[n, (TypeField::TPercent, 33)]).check(), Ok(()));
+    }
+
+    #[test]
+    fn check_rejects_invalid_thresholds() {
+        let n = (TypeField::N, 31);
+        for values in [
+            [n, (TypeField::T, 3), (TypeField::TRatio, 3)],
+            [n, (TypeField::T, 3), (TypeField::TPercent, 10)],
+            [n, (TypeField::TRatio, 3), (TypeField::TPercent, 10)],
+        ] {
+            assert_eq!(
+                fields(&values).check(),
+                Err("only one of t, t_ratio and t_percent can be given")
+            );
+        }
+        assert_eq!(
+            fields(&[n, (TypeField::T, 11)]).check(),
+            Err("n must be at least 3t + 1")
+        );
+        assert_eq!(
+            fields(&[n, (TypeField::T, 5), (TypeField::F, 11)]).check(),
+            Err("n must be at least 3f + 1")
+        );
+        assert_eq!(
+            fields(&[n, (TypeField::T, 5), (TypeField::D, 9)]).check(),
+            Err("d must be at least 2f")
+        );
+        assert_eq!(
+            fields(&[n, (TypeField::T, 5), (TypeField::D, 26)]).check(),
+            Err("d must be at most n - f - 1")
+        );
+        assert_eq!(
+            fields(&[n, (TypeField::T, 5), (TypeField::NbByz, 31)]).check(),
+            Err("an honest dealer needs nb_byz to be below n")
+        );
+        let over_degree = (TypeField::ByzComp, ByzComp::OverDegree.to_u16());
+        assert_eq!(
+            fields(&[n, (TypeField::T, 5), (TypeField::NbByz, 1), over_degree]).check(),
+            Err("an over-degree dealer is out of scope, nothing bounds the committed degree")
+        );
+    }
+
+    #[test]
+    fn crashes_count_in_the_faults() {
+        let (n, t) = ((TypeField::N, 31), (TypeField::T, 5));
+        let crashes = |nb_byz, nb_crash| {
+            fields(&[
+                n,
+                t,
+                (TypeField::NbByz, nb_byz),
+                (TypeField::NbCrash, nb_crash),
+            ])
+            .check()
+        };
+        assert_eq!(crashes(2, 3), Ok(()));
+        assert_eq!(crashes(8, 0), Ok(()));
+        assert_eq!(crashes(3, 3), Err("nb_byz + nb_crash must be at most f"));
+    }
+
+    #[test]
+    fn large_thresholds_do_not_overflow() {
+        let n = (TypeField::N, u16::MAX);
+        assert_eq!(
+            fields(&[n, (TypeField::T, 30000)]).check(),
+            Err("n must be at least 3t + 1")
+        );
+        assert_eq!(
+            fields(&[n, (TypeField::T, 20000), (TypeField::F, 40000)]).check(),
+            Err("n must be at least 3f + 1")
+        );
+        assert_eq!(
+            fields(&[n, (TypeField::T, 20000), (TypeField::D, 50000)]).check(),
+            Err("d must be at most n - f - 1")
+        );
+        assert_eq!(
+            fields(&[n, (TypeField::T, 17000), (TypeField::Decoding, 1)]).check(),
+            Err("decoding needs d + 2f + 1 nodes to correct f wrong shares")
+        );
+    }
+}
@@ -1,6 +1,9 @@
 pub use super::args::*;
+pub use super::costs::*;
 pub use super::fields::*;
 pub use super::gnu::*;
+pub use super::network::*;
+pub use super::placement::*;
 pub use super::result_fields::*;
 pub use super::variations::*;
 
@@ -9,6 +12,7 @@ pub type JsonMap = Map<String, JsonValue>;
 pub use crate::as_number;
 pub use crate::ByzComp;
 pub use crate::Evaluation;
+pub use crate::Step;
 pub use serde_json::Number;
 pub use std::collections::HashMap;
 pub use std::default::Default;
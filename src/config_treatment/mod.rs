@@ -1,7 +1,10 @@
 pub mod args;
+pub mod costs;
 pub mod fields;
 pub mod gnu;
 pub mod include;
+pub mod network;
+pub mod placement;
 pub mod result_fields;
 pub mod subargs;
 pub mod variations;
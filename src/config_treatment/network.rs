@@ -0,0 +1,451 @@
+use super::include::*;
+use crate::wire_struct;
+use rand::Rng;
+
+as_number!(
+    u8,
+    enum Distribution {
+        Uniform,
+        Normal,
+        Exponential,
+    }
+);
+
+pub static DISTRIBUTION_NAMES: [&str; 3] = ["uniform", "normal", "exponential"];
+
+wire_struct!(
+    /// Emulated link between two nodes, all durations in ms.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Link {
+        latency: u32,
+        /// Spread of the latency: the half width of the uniform distribution or the standard
+        /// deviation of the normal one. The exponential one has `latency` as its mean.
+        jitter: u32,
+        distribution: Distribution,
+        /// In kB/s, 0 for an unlimited link.
+        bandwidth: u32,
+        /// Percentage of the messages lost.
+        loss: u8,
+    }
+);
+
+impl Default for Link {
+    fn default() -> Self {
+        Link {
+            latency: 0,
+            jitter: 0,
+            distribution: Distribution::Uniform,
+            bandwidth: 0,
+            loss: 0,
+        }
+    }
+}
+
+impl Link {
+    fn from_serde(obj: &JsonMap, mut link: Link) -> Result<Link, &'static str> {
+        for (key, value) in obj {
+            let number = || value.as_u64().ok_or("Invalid number in the network");
+            match key as &str {
+                "latency" => link.latency = number()? as u32,
+                "jitter" => link.jitter = number()? as u32,
+                "bandwidth" => link.bandwidth = number()? as u32,
+                "loss" => link.loss = number()?.min(u8::MAX as u64) as u8,
+                "distribution" => {
+                    link.distribution = value
+                        .as_str()
+                        .and_then(|s| DISTRIBUTION_NAMES.iter().position(|d| *d == s))
+                        .map(|i| (i as u8).into())
+                        .ok_or("Unknown latency distribution")?
+                },
+                "groups" | "links" | "partitions" => (),
+                _ => return Err("Invalid key for a link"),
+            }
+        }
+        Ok(link)
+    }
+
+    fn to_serde(self) -> JsonValue {
+        let mut obj = JsonMap::new();
+        obj.insert("latency".to_string(), self.latency.into());
+        obj.insert("jitter".to_string(), self.jitter.into());
+        obj.insert(
+            "distribution".to_string(),
+            DISTRIBUTION_NAMES[Into::<u8>::into(self.distribution) as usize].into(),
+        );
+        obj.insert("bandwidth".to_string(), self.bandwidth.into());
+        obj.insert("loss".to_string(), self.loss.into());
+        JsonValue::Object(obj)
+    }
+
+    fn check(&self) -> Result<(), &'static str> {
+        if self.loss > 100 {
+            return Err("loss must be a percentage");
+        }
+        Ok(())
+    }
+
+    pub fn is_perfect(&self) -> bool {
+        *self == Link::default()
+    }
+
+    pub fn drops(&self, rng: &mut impl Rng) -> bool {
+        self.loss > 0 && rng.gen_range(0..100) < self.loss
+    }
+
+    /// Propagation delay of a message, in ms.
+    pub fn sample_latency(&self, rng: &mut impl Rng) -> f64 {
+        let (latency, jitter) = (self.latency as f64, self.jitter as f64);
+        let sample = match self.distribution {
+            Distribution::Uniform if jitter > 0.0 => latency + rng.gen_range(-jitter..=jitter),
+            Distribution::Uniform => latency,
+            Distribution::Normal => {
+                // Box-Muller transform.
+                let (u1, u2) = (1.0 - rng.gen::<f64>(), rng.gen::<f64>());
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+                latency + jitter * z
+            },
+            Distribution::Exponential => -latency * (1.0 - rng.gen::<f64>()).ln(),
+        };
+        sample.max(0.0)
+    }
+
+    /// Time in ms taken to put `len` bytes on the link.
+    pub fn transmission(&self, len: usize) -> f64 {
+        match self.bandwidth {
+            0 => 0.0,
+            bandwidth => len as f64 / bandwidth as f64,
+        }
+    }
+}
+
+/// A window in ms since the setup of the sharing during which a link is cut, `None` when it
+/// never heals.
+pub type Cut = (u32, Option<u32>);
+
+wire_struct!(
+    /// Splits the nodes into `groups` from `at` ms after the setup of the sharing, for
+    /// `duration` ms or until the next setup when it is 0. The nodes of no group form one more
+    /// group.
+    #[derive(Debug, Clone, PartialEq)]
+    struct Partition {
+        at: u32,
+        groups: Vec<Vec<u16>>,
+        duration: u32,
+    }
+);
+
+impl Partition {
+    fn from_serde(value: &JsonValue) -> Result<Partition, &'static str> {
+        let obj = value
+            .as_object()
+            .ok_or("Invalid partition, expected an object")?;
+        let mut partition = Partition {
+            at: 0,
+            groups: Vec::new(),
+            duration: 0,
+        };
+        for (key, value) in obj {
+            match key as &str {
+                "at_ms" => partition.at = value.as_u64().ok_or("Invalid at_ms")? as u32,
+                "duration_ms" => {
+                    partition.duration = value.as_u64().ok_or("Invalid duration_ms")? as u32
+                },
+                "groups" => partition.groups = groups_from_serde(value)?,
+                _ => return Err("Invalid key for a partition"),
+            }
+        }
+        Ok(partition)
+    }
+
+    fn to_serde(&self) -> JsonValue {
+        let mut obj = JsonMap::new();
+        obj.insert("at_ms".to_string(), self.at.into());
+        obj.insert("groups".to_string(), self.groups.clone().into());
+        obj.insert("duration_ms".to_string(), self.duration.into());
+        JsonValue::Object(obj)
+    }
+
+    fn check(&self, n: u16) -> Result<(), &'static str> {
+        let nodes = self.groups.iter().flatten().collect::<Vec<_>>();
+        if nodes.iter().any(|i| **i >= n) {
+            return Err("the partition groups must list nodes below n");
+        }
+        if (1..nodes.len()).any(|i| nodes[..i].contains(&nodes[i])) {
+            return Err("a node must be in at most one partition group");
+        }
+        Ok(())
+    }
+
+    fn separates(&self, a: u16, b: u16) -> bool {
+        let group = |i| self.groups.iter().position(|group| group.contains(&i));
+        group(a) != group(b)
+    }
+}
+
+fn groups_from_serde(value: &JsonValue) -> Result<Vec<Vec<u16>>, &'static str> {
+    match value {
+        JsonValue::Array(groups) => groups
+            .iter()
+            .map(|group| match group {
+                JsonValue::Array(nodes) => Ok(nodes.iter().map(serde_n_to_u16).collect()),
+                _ => Err("Invalid group, expected a list of nodes"),
+            })
+            .collect(),
+        _ => Err("Invalid groups, expected a list"),
+    }
+}
+
+wire_struct!(
+    /// Links emulated between the nodes, set by the `network` block of the setup. The nodes
+    /// of a group use the matching link of `links` to reach the nodes of another group, every
+    /// other pair uses `default`.
+    #[derive(Debug, Clone, Default, PartialEq)]
+    struct NetworkEmulation {
+        default: Link,
+        groups: Vec<Vec<u16>>,
+        /// `links[i][j]` goes from the nodes of `groups[i]` to the ones of `groups[j]`.
+        links: Vec<Vec<Link>>,
+        partitions: Vec<Partition>,
+    }
+);
+
+impl NetworkEmulation {
+    /// Either a single link for all of the pairs, or `groups` with the matrix of their
+    /// `links`, the other keys giving the link of the nodes outside of the groups. The links of
+    /// the matrix only override the keys they give.
+    pub fn from_serde(value: &JsonValue) -> Result<NetworkEmulation, &'static str> {
+        let obj = value
+            .as_object()
+            .ok_or("Invalid network, expected an object")?;
+        let default = Link::from_serde(obj, Link::default())?;
+        let groups = match obj.get("groups") {
+            Some(groups) => groups_from_serde(groups)?,
+            None => Vec::new(),
+        };
+        let links = match obj.get("links") {
+            Some(JsonValue::Array(rows)) => rows
+                .iter()
+                .map(|row| match row {
+                    JsonValue::Array(row) => row
+                        .iter()
+                        .map(|link| match link {
+                            JsonValue::Object(link) => Link::from_serde(link, default),
+                            _ => Err("Invalid link, expected an object"),
+                        })
+                        .collect(),
+                    _ => Err("Invalid links, expected a matrix"),
+                })
+                .collect::<Result<Vec<Vec<Link>>, _>>()?,
+            None => Vec::new(),
+            _ => return Err("Invalid links, expected a matrix"),
+        };
+        let partitions = match obj.get("partitions") {
+            Some(JsonValue::Array(partitions)) => partitions
+                .iter()
+                .map(Partition::from_serde)
+                .collect::<Result<Vec<Partition>, _>>()?,
+            None => Vec::new(),
+            _ => return Err("Invalid partitions, expected a list"),
+        };
+        Ok(NetworkEmulation {
+            default,
+            groups,
+            links,
+            partitions,
+        })
+    }
+
+    pub fn to_serde(&self) -> JsonValue {
+        let mut obj = extract_serde_obj(&self.default.to_serde()).clone();
+        if !self.groups.is_empty() {
+            obj.insert("groups".to_string(), self.groups.clone().into());
+            obj.insert(
+                "links".to_string(),
+                self.links
+                    .iter()
+                    .map(|row| row.iter().copied().map(Link::to_serde).collect())
+                    .collect::<Vec<JsonValue>>()
+                    .into(),
+            );
+        }
+        if !self.partitions.is_empty() {
+            obj.insert(
+                "partitions".to_string(),
+                self.partitions
+                    .iter()
+                    .map(Partition::to_serde)
+                    .collect::<Vec<JsonValue>>()
+                    .into(),
+            );
+        }
+        JsonValue::Object(obj)
+    }
+
+    pub fn check(&self, n: u16) -> Result<(), &'static str> {
+        self.default.check()?;
+        if self.links.len() != self.groups.len()
+            || self.links.iter().any(|row| row.len() != self.groups.len())
+        {
+            return Err("links must be a square matrix with a row per group");
+        }
+        for link in self.links.iter().flatten() {
+            link.check()?;
+        }
+        let nodes = self.groups.iter().flatten().collect::<Vec<_>>();
+        if nodes.iter().any(|i| **i >= n) {
+            return Err("the network groups must list nodes below n");
+        }
+        if (1..nodes.len()).any(|i| nodes[..i].contains(&nodes[i])) {
+            return Err("a node must be in at most one network group");
+        }
+        for partition in &self.partitions {
+            partition.check(n)?;
+        }
+        Ok(())
+    }
+
+    fn group(&self, i: u16) -> Option<usize> {
+        self.groups.iter().position(|group| group.contains(&i))
+    }
+
+    /// The link from node `from` to node `to`.
+    pub fn link(&self, from: u16, to: u16) -> Link {
+        match (self.group(from), self.group(to)) {
+            (Some(from), Some(to)) => self.links[from][to],
+            _ => self.default,
+        }
+    }
+
+    /// The windows during which the partitions cut the link between `from` and `to`.
+    pub fn cuts(&self, from: u16, to: u16) -> Vec<Cut> {
+        self.partitions
+            .iter()
+            .filter(|partition| partition.separates(from, to))
+            .map(|partition| {
+                let heal = (partition.duration != 0).then(|| partition.at + partition.duration);
+                (partition.at, heal)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Wire;
+    use rand::{rngs::StdRng, SeedableRng};
+    use serde_json::json;
+
+    fn grouped() -> NetworkEmulation {
+        NetworkEmulation::from_serde(&json!({
+            "latency": 5,
+            "groups": [[0, 1], [2]],
+            "links": [
+                [{}, {"latency": 100, "loss": 10}],
+                [{"latency": 100, "distribution": "normal", "jitter": 20}, {"bandwidth": 50}],
+            ],
+            "partitions": [
+                {"at_ms": 0, "groups": [[0]], "duration_ms": 300},
+                {"at_ms": 500, "groups": [[0, 1], [2]]},
+            ],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn links_between_groups() {
+        let network = grouped();
+        assert_eq!(network.check(4), Ok(()));
+        assert_eq!(network.link(0, 1).latency, 5);
+        assert_eq!(network.link(1, 2).loss, 10);
+        assert_eq!(network.link(2, 0).distribution, Distribution::Normal);
+        assert_eq!(network.link(2, 2).bandwidth, 50);
+        // Node 3 is in no group.
+        assert_eq!(network.link(3, 2), network.default);
+        assert_eq!(network.link(0, 3), network.default);
+    }
+
+    #[test]
+    fn partitions_cut_the_links_between_groups() {
+        let network = grouped();
+        assert_eq!(network.cuts(0, 1), vec![(0, Some(300))]);
+        assert_eq!(network.cuts(1, 0), vec![(0, Some(300))]);
+        assert_eq!(network.cuts(0, 2), vec![(0, Some(300)), (500, None)]);
+        // The nodes of no group are together.
+        assert_eq!(network.cuts(2, 3), vec![(500, None)]);
+        assert_eq!(network.cuts(1, 2), vec![(500, None)]);
+        assert!(network.cuts(0, 0).is_empty());
+    }
+
+    #[test]
+    fn serde_and_wire_round_trip() {
+        let network = grouped();
+        assert_eq!(
+            NetworkEmulation::from_serde(&network.to_serde()),
+            Ok(network.clone())
+        );
+        assert_eq!(
+            NetworkEmulation::from_bytes(&network.to_bytes()),
+            Ok(network)
+        );
+    }
+
+    #[test]
+    fn invalid_networks_are_rejected() {
+        for (value, err) in [
+            (
+                json!({"groups": [[0], [1]], "links": [[{}]]}),
+                "links must be a square matrix with a row per group",
+            ),
+            (
+                json!({"groups": [[0], [4]], "links": [[{}, {}], [{}, {}]]}),
+                "the network groups must list nodes below n",
+            ),
+            (
+                json!({"groups": [[0], [0]], "links": [[{}, {}], [{}, {}]]}),
+                "a node must be in at most one network group",
+            ),
+            (json!({"loss": 101}), "loss must be a percentage"),
+            (
+                json!({"partitions": [{"groups": [[4]]}]}),
+                "the partition groups must list nodes below n",
+            ),
+            (
+                json!({"partitions": [{"groups": [[1], [1]]}]}),
+                "a node must be in at most one partition group",
+            ),
+        ] {
+            assert_eq!(
+                NetworkEmulation::from_serde(&value).unwrap().check(4),
+                Err(err)
+            );
+        }
+        assert!(NetworkEmulation::from_serde(&json!({"distribution": "pareto"})).is_err());
+        assert!(NetworkEmulation::from_serde(&json!({"latence": 3})).is_err());
+        assert!(NetworkEmulation::from_serde(&json!({"partitions": [{"at": 3}]})).is_err());
+    }
+
+    #[test]
+    fn samples_follow_the_link() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let link = Link {
+            latency: 50,
+            jitter: 10,
+            ..Link::default()
+        };
+        assert!((0..1000).all(|_| (40.0..=60.0).contains(&link.sample_latency(&mut rng))));
+        let lossy = Link {
+            loss: 30,
+            ..Link::default()
+        };
+        let lost = (0..10000).filter(|_| lossy.drops(&mut rng)).count();
+        assert!(lost.abs_diff(3000) < 300);
+        assert!(!(0..1000).any(|_| Link::default().drops(&mut rng)));
+        let slow = Link {
+            bandwidth: 10,
+            ..Link::default()
+        };
+        assert_eq!(slow.transmission(1000), 100.0);
+        assert_eq!(Link::default().transmission(1000), 0.0);
+    }
+}
@@ -0,0 +1,148 @@
+use super::include::*;
+use rand::{rngs::StdRng, seq::index::sample, SeedableRng};
+
+/// Which nodes of the network are byzantine.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ByzPlacement {
+    First,
+    Last,
+    /// `nb_byz` nodes drawn with the given seed.
+    Random(u64),
+    /// The first `nb_byz` nodes of the list.
+    List(Vec<u16>),
+}
+
+impl ByzPlacement {
+    pub fn from_serde(value: &JsonValue) -> Result<ByzPlacement, &'static str> {
+        match value {
+            JsonValue::String(s) if s == "first" => Ok(ByzPlacement::First),
+            JsonValue::String(s) if s == "last" => Ok(ByzPlacement::Last),
+            JsonValue::Object(obj) => match obj.get("random") {
+                Some(JsonValue::Number(seed)) if obj.len() == 1 => {
+                    Ok(ByzPlacement::Random(seed.as_u64().ok_or("Invalid seed")?))
+                },
+                _ => Err("Invalid byz_placement, expected {\"random\": seed}"),
+            },
+            JsonValue::Array(arr) => {
+                Ok(ByzPlacement::List(arr.iter().map(serde_n_to_u16).collect()))
+            },
+            _ => Err("Invalid byz_placement"),
+        }
+    }
+
+    pub fn to_serde(&self) -> JsonValue {
+        match self {
+            ByzPlacement::First => "first".into(),
+            ByzPlacement::Last => "last".into(),
+            ByzPlacement::Random(seed) => {
+                let mut obj = JsonMap::new();
+                obj.insert("random".to_string(), (*seed).into());
+                JsonValue::Object(obj)
+            },
+            ByzPlacement::List(list) => list.clone().into(),
+        }
+    }
+
+    /// Indices of the `nb_byz` byzantine nodes among `n`.
+    pub fn byzantine(&self, n: u16, nb_byz: u16) -> Vec<u16> {
+        match self {
+            ByzPlacement::First => (0..nb_byz).collect(),
+            ByzPlacement::Last => (n - nb_byz..n).collect(),
+            ByzPlacement::Random(seed) => sample(
+                &mut StdRng::seed_from_u64(*seed),
+                n as usize,
+                nb_byz as usize,
+            )
+            .into_iter()
+            .map(|i| i as u16)
+            .collect(),
+            ByzPlacement::List(list) => list[..nb_byz as usize].to_vec(),
+        }
+    }
+
+    pub fn check(&self, n: u16, nb_byz: u16) -> Result<(), &'static str> {
+        if nb_byz > n {
+            return Err("nb_byz must be at most n");
+        }
+        if let ByzPlacement::List(list) = self {
+            if list.len() < nb_byz as usize {
+                return Err("byz_placement must list at least nb_byz nodes");
+            }
+            let byzantine = &list[..nb_byz as usize];
+            if byzantine.iter().any(|i| *i >= n) {
+                return Err("byz_placement must list nodes below n");
+            }
+            if (1..byzantine.len()).any(|i| byzantine[..i].contains(&byzantine[i])) {
+                return Err("byz_placement must not list a node twice");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_and_last() {
+        assert_eq!(ByzPlacement::First.byzantine(7, 3), vec![0, 1, 2]);
+        assert_eq!(ByzPlacement::Last.byzantine(7, 3), vec![4, 5, 6]);
+        assert!(ByzPlacement::Last.byzantine(7, 0).is_empty());
+    }
+
+    #[test]
+    fn random_is_determined_by_the_seed() {
+        let byzantine = ByzPlacement::Random(12).byzantine(100, 30);
+        assert_eq!(byzantine, ByzPlacement::Random(12).byzantine(100, 30));
+        assert_ne!(byzantine, ByzPlacement::Random(13).byzantine(100, 30));
+        assert_eq!(byzantine.len(), 30);
+        assert!(byzantine.iter().all(|i| *i < 100));
+        assert!((1..byzantine.len()).all(|i| !byzantine[..i].contains(&byzantine[i])));
+    }
+
+    #[test]
+    fn list_keeps_its_first_nodes() {
+        let placement = ByzPlacement::List(vec![5, 2, 6, 0]);
+        assert_eq!(placement.check(7, 3), Ok(()));
+        assert_eq!(placement.byzantine(7, 3), vec![5, 2, 6]);
+    }
+
+    #[test]
+    fn invalid_placements_are_rejected() {
+        assert_eq!(
+            ByzPlacement::First.check(7, 8),
+            Err("nb_byz must be at most n")
+        );
+        assert_eq!(
+            ByzPlacement::List(vec![1, 2]).check(7, 3),
+            Err("byz_placement must list at least nb_byz nodes")
+        );
+        assert_eq!(
+            ByzPlacement::List(vec![1, 7, 2]).check(7, 3),
+            Err("byz_placement must list nodes below n")
+        );
+        assert_eq!(
+            ByzPlacement::List(vec![1, 2, 1]).check(7, 3),
+            Err("byz_placement must not list a node twice")
+        );
+        // Only the first nb_byz nodes of the list are checked.
+        assert_eq!(ByzPlacement::List(vec![1, 2, 2, 9]).check(7, 2), Ok(()));
+    }
+
+    #[test]
+    fn placements_round_trip_through_json() {
+        for placement in [
+            ByzPlacement::First,
+            ByzPlacement::Last,
+            ByzPlacement::Random(42),
+            ByzPlacement::List(vec![3, 1]),
+        ] {
+            assert_eq!(
+                ByzPlacement::from_serde(&placement.to_serde()),
+                Ok(placement)
+            );
+        }
+        assert!(ByzPlacement::from_serde(&"middle".into()).is_err());
+    }
+}
@@ -1,11 +1,10 @@
 pub type Duration = u128; // in ms
 pub type ResultField = String;
 use crate::as_number;
-use crate::ErrorCode;
-use byteorder::{ByteOrder, LittleEndian};
-use std::ops::AddAssign;
+use crate::{Bytes, Decoded, ErrorCode, Step, Traffic, Wire, COMMAND_NAMES};
+use std::ops::{AddAssign, Range};
 
-pub static POSSIBLE_LATENCY_RESULT_FIELD: [&str; 7] = [
+pub static POSSIBLE_LATENCY_RESULT_FIELD: [&str; 14] = [
     "verify",
     "dealing",
     "first_receiv",
@@ -13,15 +12,70 @@ pub static POSSIBLE_LATENCY_RESULT_FIELD: [&str; 7] = [
     "messages_computing",
     "total_sharing",
     "total_reconstruct",
+    "honnest_acts",
+    "random_acts",
+    "sleeper_acts",
+    "wrong_shares",
+    // Percentages of the runs that timed out, that a node ended with an error and that had
+    // more than `f` byzantine and crashed nodes.
+    "timed_out",
+    "failed",
+    "over_faults",
 ];
 
 pub static POSSIBLE_DEBIT_RESULT_FIELD: [&str; 2] = ["sharing", "reconstruct"];
 
-const NB_FIELD: usize = 9;
-pub const RESULT_FIELDS_SIZE: usize = NB_FIELD * 16 + 1;
+/// Traffic of a node during the sharing, averaged over the nodes, and during the
+/// reconstruction with the `_reconstruct` suffix. The name of a `CommandCode` after a dot only
+/// counts its frames, as `bytes_sent_reconstruct.protocol`.
+pub static TRAFFIC_RESULT_FIELD: [&str; 4] = [
+    "messages_sent",
+    "bytes_sent",
+    "messages_received",
+    "bytes_received",
+];
+
+const NB_FIELD: usize = 16;
+/// Counters of the traffic, by phase, then by field of `TRAFFIC_RESULT_FIELD`, then by code.
+const TRAFFIC_SIZE: usize = 2 * TRAFFIC_RESULT_FIELD.len() * COMMAND_NAMES.len();
 
 pub fn result_exists(res: &str) -> bool {
-    POSSIBLE_DEBIT_RESULT_FIELD.contains(&res) || POSSIBLE_LATENCY_RESULT_FIELD.contains(&res)
+    POSSIBLE_DEBIT_RESULT_FIELD.contains(&res)
+        || POSSIBLE_LATENCY_RESULT_FIELD.contains(&res)
+        || traffic_field(res).is_some()
+}
+
+/// Whether the result is measured during the reconstruction.
+pub fn is_reconstruct_result(res: &str) -> bool {
+    res == "total_reconstruct"
+        || traffic_field(res).is_some_and(|(step, _)| step == Step::Reconstruct)
+}
+
+pub fn is_traffic_result(res: &str) -> bool {
+    traffic_field(res).is_some()
+}
+
+/// The phase of a field of the traffic, with the counters it sums.
+fn traffic_field(field: &str) -> Option<(Step, Range<usize>)> {
+    let (field, code) = match field.split_once('.') {
+        Some((field, name)) => (field, Some(COMMAND_NAMES.iter().position(|c| *c == name)?)),
+        None => (field, None),
+    };
+    let (field, step) = match field.strip_suffix("_reconstruct") {
+        Some(field) => (field, Step::Reconstruct),
+        None => (field, Step::Sharing),
+    };
+    let metric = TRAFFIC_RESULT_FIELD.iter().position(|f| *f == field)?;
+    let start = traffic_index(step, metric, 0);
+    Some(match code {
+        Some(code) => (step, start + code..start + code + 1),
+        None => (step, start..start + COMMAND_NAMES.len()),
+    })
+}
+
+fn traffic_index(step: Step, metric: usize, code: usize) -> usize {
+    let phase = (step == Step::Reconstruct) as usize;
+    (phase * TRAFFIC_RESULT_FIELD.len() + metric) * COMMAND_NAMES.len() + code
 }
 
 as_number!(
@@ -34,15 +88,24 @@ as_number!(
         MessagesComputing,
         Total,
         Reconstruction,
+        HonnestActs,
+        RandomActs,
+        SleeperActs,
+        WrongShares,
+        TimedOut,
+        Failed,
+        OverFaults,
         DebitSharing,
         DebitReconstruct,
     }
 );
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct ResultFields {
     results: Vec<Duration>,
     code: ErrorCode,
+    decoded: Option<Decoded>,
+    traffic: Vec<u128>,
 }
 
 impl Default for ResultFields {
@@ -50,6 +113,8 @@ impl Default for ResultFields {
         ResultFields {
             results: Vec::new(),
             code: ErrorCode::OK,
+            decoded: None,
+            traffic: Vec::new(),
         }
     }
 }
@@ -61,7 +126,11 @@ impl AddAssign for ResultFields {
             .zip(other.results.iter())
             .for_each(|(my, his)| {
                 *my = *my + *his;
-            })
+            });
+        self.traffic
+            .iter_mut()
+            .zip(other.traffic.iter())
+            .for_each(|(my, his)| *my += *his)
     }
 }
 
@@ -70,6 +139,8 @@ impl ResultFields {
         ResultFields {
             code: ErrorCode::OK,
             results: vec![0; NB_FIELD],
+            decoded: None,
+            traffic: vec![0; TRAFFIC_SIZE],
         }
     }
 
@@ -85,33 +156,51 @@ impl ResultFields {
         self.results[Into::<usize>::into(i)] = v;
     }
 
+    pub fn incr(&mut self, i: TypeResultField) {
+        self.results[Into::<usize>::into(i)] += 1;
+    }
+
     pub fn extract(&mut self) -> ResultFields {
         let res = self.clone();
         *self = ResultFields::new();
         res
     }
 
-    pub fn to_bytes(&self, bytes: &mut [u8], code: ErrorCode) {
-        bytes[0] = code.into();
-        let bytes = &mut bytes[1..];
-        self.results
-            .iter()
-            .enumerate()
-            .for_each(|(i, r)| LittleEndian::write_u128(&mut bytes[i * 16..], *r));
+    pub fn set_code(&mut self, code: ErrorCode) {
+        self.code = code;
     }
 
-    pub fn from_bytes(bytes: &[u8]) -> Self {
-        let code: ErrorCode = bytes[0].into();
-        let bytes = &bytes[1..];
-        ResultFields {
-            code,
-            results: (0..NB_FIELD)
-                .map(|i| LittleEndian::read_u128(&bytes[i * 16..]))
-                .collect(),
+    /// Secret reconstructed by decoding, with the nodes whose shares were corrected.
+    pub fn decoded(&self) -> Option<&Decoded> {
+        self.decoded.as_ref()
+    }
+
+    pub fn set_decoded(&mut self, secret: u128, wrong: Vec<u16>) {
+        self.set(TypeResultField::WrongShares, wrong.len() as Duration);
+        self.decoded = Some(Decoded { secret, wrong });
+    }
+
+    /// Sets the traffic of a node during `step`.
+    pub fn set_traffic(&mut self, step: Step, traffic: &Traffic) {
+        let counters = [traffic.sent, traffic.received];
+        for (code, _) in COMMAND_NAMES.iter().enumerate() {
+            for (i, counts) in counters.iter().enumerate() {
+                let (frames, bytes) = counts[code];
+                self.traffic[traffic_index(step, 2 * i, code)] = frames as u128;
+                self.traffic[traffic_index(step, 2 * i + 1, code)] = bytes as u128;
+            }
         }
     }
 
+    /// Divides the traffic summed over `nodes` outputs.
+    pub fn average_traffic(&mut self, nodes: u128) {
+        self.traffic.iter_mut().for_each(|count| *count /= nodes)
+    }
+
     pub fn get_from_str(&self, field: &str) -> Duration {
+        if let Some((_, counters)) = traffic_field(field) {
+            return self.traffic[counters].iter().sum();
+        }
         self.results[POSSIBLE_LATENCY_RESULT_FIELD
             .iter()
             .chain(POSSIBLE_DEBIT_RESULT_FIELD.iter())
@@ -119,3 +208,78 @@ impl ResultFields {
             .unwrap()]
     }
 }
+
+impl Wire for ResultFields {
+    fn write(&self, buf: &mut Vec<u8>) {
+        self.code.write(buf);
+        self.results.write(buf);
+        self.decoded.write(buf);
+        self.traffic.write(buf);
+    }
+
+    fn read(bytes: &mut Bytes<'_>) -> Result<Self, &'static str> {
+        let code = ErrorCode::read(bytes)?;
+        let results = Vec::<Duration>::read(bytes)?;
+        if results.len() != NB_FIELD {
+            return Err("Invalid number of results");
+        }
+        let decoded = Option::<Decoded>::read(bytes)?;
+        let traffic = Vec::<u128>::read(bytes)?;
+        if traffic.len() != TRAFFIC_SIZE {
+            return Err("Invalid size of the traffic");
+        }
+        Ok(ResultFields {
+            results,
+            code,
+            decoded,
+            traffic,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CommandCode;
+
+    #[test]
+    fn traffic_fields_sum_their_codes() {
+        let mut traffic = Traffic::default();
+        traffic.sent[CommandCode::PROTOCOL as usize] = (3, 300);
+        traffic.sent[CommandCode::KEY as usize] = (1, 50);
+        traffic.received[CommandCode::SETUP as usize] = (1, 20);
+        let mut result = ResultFields::new();
+        result.set_traffic(Step::Reconstruct, &traffic);
+        let mut other = ResultFields::new();
+        other.set_traffic(Step::Reconstruct, &traffic);
+        result += other;
+        result.average_traffic(2);
+        assert_eq!(result.get_from_str("messages_sent_reconstruct"), 4);
+        assert_eq!(result.get_from_str("bytes_sent_reconstruct"), 350);
+        assert_eq!(result.get_from_str("bytes_sent_reconstruct.protocol"), 300);
+        assert_eq!(result.get_from_str("bytes_received_reconstruct.setup"), 20);
+        assert_eq!(result.get_from_str("bytes_sent"), 0);
+        assert_eq!(ResultFields::from_bytes(&result.to_bytes()), Ok(result));
+    }
+
+    #[test]
+    fn traffic_field_names() {
+        for field in [
+            "bytes_sent",
+            "messages_received_reconstruct",
+            "bytes_sent.reconstruct",
+        ] {
+            assert!(result_exists(field));
+        }
+        assert!(is_reconstruct_result("bytes_received_reconstruct.key"));
+        assert!(!is_reconstruct_result("bytes_sent.reconstruct"));
+        for field in [
+            "bytes",
+            "bytes_sent.output",
+            "bytes_sent_sharing",
+            "bytes_sent.",
+        ] {
+            assert!(!result_exists(field));
+        }
+    }
+}
@@ -5,13 +5,18 @@ pub struct SubArgs {
     latency_hmt: usize,
     debit_hmt: usize,
     debit_duration: usize,
+    protocol: String,
+    byz_placement: ByzPlacement,
+    network: NetworkEmulation,
+    costs: Costs,
     fields: Fields,
     variation: Variation,
     result: HashMap<ResultField, Vec<Duration>>,
 }
 
 impl SubArgs {
-    pub fn new() -> Self {
+    /// Runs `protocol` unless the setup gives another one.
+    pub fn new(protocol: &str) -> Self {
         SubArgs {
             fields: Fields::new(),
             variation: Variation::new(),
@@ -19,9 +24,71 @@ impl SubArgs {
             latency_hmt: 0,
             debit_duration: 1,
             debit_hmt: 0,
+            protocol: protocol.to_string(),
+            byz_placement: ByzPlacement::First,
+            network: NetworkEmulation::default(),
+            costs: Costs::default(),
         }
     }
 
+    pub fn protocol(&self) -> &str {
+        &self.protocol
+    }
+
+    pub fn set_protocol(&mut self, protocol: &str) {
+        self.protocol = protocol.to_string()
+    }
+
+    pub fn set_byz_placement(&mut self, byz_placement: ByzPlacement) {
+        self.byz_placement = byz_placement
+    }
+
+    pub fn network(&self) -> &NetworkEmulation {
+        &self.network
+    }
+
+    pub fn set_network(&mut self, network: NetworkEmulation) {
+        self.network = network
+    }
+
+    pub fn costs(&self) -> Costs {
+        self.costs
+    }
+
+    pub fn set_costs(&mut self, costs: Costs) {
+        self.costs = costs
+    }
+
+    /// Indices of the byzantine nodes in the current state.
+    pub fn byzantine(&self) -> Vec<u16> {
+        self.byz_placement
+            .byzantine(self.fields.n(), self.fields.get(TypeField::NbByz))
+    }
+
+    /// `protocols` are the names of the protocols the nodes are able to run.
+    pub fn check(&self, protocols: &[&str]) -> Result<(), &'static str> {
+        if !protocols.contains(&self.protocol.as_str()) {
+            return Err("Unknown protocol");
+        }
+        self.check_fields()
+    }
+
+    /// Checks the thresholds and the network of every state.
+    pub fn check_fields(&self) -> Result<(), &'static str> {
+        self.fields.check()?;
+        self.byz_placement
+            .check(self.fields.n(), self.fields.get(TypeField::NbByz))?;
+        self.network.check(self.fields.n())?;
+        // Every state of the variation is run, not only the first one.
+        for fields in self.variation.states(&self.fields) {
+            fields.check()?;
+            self.byz_placement
+                .check(fields.n(), fields.get(TypeField::NbByz))?;
+            self.network.check(fields.n())?;
+        }
+        Ok(())
+    }
+
     pub fn has_sharing(&self) -> bool {
         self.result
             .keys()
@@ -91,8 +158,9 @@ impl SubArgs {
                 Evaluation::Latency(_) => &POSSIBLE_LATENCY_RESULT_FIELD,
             };
             let hmt = self.hmt(&eval) as u128;
+            let is_latency = eval.is_latency();
             self.result.iter_mut().for_each(|(k, vec)| {
-                if fields.contains(&(k as &str)) {
+                if fields.contains(&(k as &str)) || is_latency && is_traffic_result(k) {
                     vec.push(conclusion.get_from_str(k) / hmt);
                 }
             });
@@ -125,17 +193,13 @@ impl SubArgs {
     }
 
     pub fn get_field_and_var(&self) -> (Vec<u32>, String, Vec<(String, u32)>) {
-        let (vec, field) = self.variation.get_field_and_var(self.fields.n());
-        let mut base_state = [
-            ("n", self.fields.n()),
-            ("t", self.fields.get(TypeField::TDenom)),
-            ("nb_byz", self.fields.get(TypeField::NbByz)),
-            ("byz_comp", self.fields.get(TypeField::ByzComp)),
-        ]
-        .iter()
-        .map(|(f, v)| (f.to_string(), *v as u32))
-        .collect::<Vec<_>>();
-        base_state.remove(base_state.iter().position(|(f, _)| *f == field).unwrap());
+        let (vec, field) = self.variation.get_field_and_var();
+        let base_state = STATIC_TYPE_FIELD
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| **f != field)
+            .map(|(i, f)| (f.to_string(), self.fields.get(i.into()) as u32))
+            .collect::<Vec<_>>();
         (vec, field, base_state)
     }
 
@@ -172,7 +236,7 @@ impl SubArgs {
     pub fn reconstruct(&self, eval: Evaluation) -> bool {
         match eval {
             Evaluation::Debit(_) => self.result.contains_key("reconstruct"),
-            Evaluation::Latency(_) => self.result.contains_key("total_reconstruct"),
+            Evaluation::Latency(_) => self.result.keys().any(|k| is_reconstruct_result(k)),
         }
     }
 
@@ -189,7 +253,24 @@ impl SubArgs {
         let mut map = serde_json::Map::new();
         let (variation, field, base_state) = self.get_field_and_var();
         map.insert("field".to_string(), field.into());
+        map.insert("protocol".to_string(), self.protocol.clone().into());
+        map.insert("byz_placement".to_string(), self.byz_placement.to_serde());
+        map.insert("network".to_string(), self.network.to_serde());
+        map.insert("costs".to_string(), self.costs.to_serde());
         map.insert("variation".to_string(), variation.into());
+        let states = self.variation.states(&self.fields);
+        let mut used = JsonMap::new();
+        for (key, threshold) in [
+            ("t", Fields::t as fn(&Fields) -> u16),
+            ("d", Fields::d),
+            ("f", Fields::f),
+        ] {
+            used.insert(
+                key.to_string(),
+                states.iter().map(threshold).collect::<Vec<u16>>().into(),
+            );
+        }
+        map.insert("thresholds".to_string(), JsonValue::Object(used));
         for (f, v) in base_state {
             map.insert(f, v.into());
         }
@@ -204,3 +285,34 @@ impl SubArgs {
         JsonValue::Object(obj)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subargs(n: &[u16]) -> SubArgs {
+        let mut subarg = SubArgs::new("avss_simpl");
+        subarg.set_field_from_str("byz_comp", ByzComp::Honnest.to_u16());
+        subarg.set_field_from_str("t", 5);
+        let n = n.iter().map(|n| JsonValue::from(*n)).collect::<Vec<_>>();
+        subarg.set_variation_data(VariationData::from_serde_str("n", &n).unwrap());
+        subarg
+    }
+
+    #[test]
+    fn every_state_is_checked() {
+        assert_eq!(subargs(&[16, 31]).check(&["avss_simpl"]), Ok(()));
+        assert_eq!(
+            subargs(&[31, 16, 7]).check(&["avss_simpl"]),
+            Err("n must be at least 3t + 1")
+        );
+    }
+
+    #[test]
+    fn the_protocol_must_be_run_by_the_nodes() {
+        let mut subarg = subargs(&[16]);
+        subarg.set_protocol("bivariate_avss");
+        assert_eq!(subarg.check(&["avss_simpl"]), Err("Unknown protocol"));
+        assert_eq!(subarg.check(&["avss_simpl", "bivariate_avss"]), Ok(()));
+    }
+}
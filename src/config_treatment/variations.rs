@@ -120,17 +120,22 @@ impl Variation {
         }
     }
 
-    pub fn get_field_and_var(&self, n: u16) -> (Vec<u32>, String) {
+    /// The fields of every state of the variation, from the ones of `base`.
+    pub fn states(&self, base: &Fields) -> Vec<Fields> {
+        self.data
+            .variation
+            .iter()
+            .map(|v| {
+                let mut fields = base.clone();
+                fields.set(self.data.field, *v);
+                fields
+            })
+            .collect()
+    }
+
+    pub fn get_field_and_var(&self) -> (Vec<u32>, String) {
         (
-            if self.data.field == TypeField::TDenom {
-                self.data
-                    .variation
-                    .iter()
-                    .map(|t| ((n - 1) / *t) as u32)
-                    .collect()
-            } else {
-                self.data.variation.iter().map(|v| *v as u32).collect()
-            },
+            self.data.variation.iter().map(|v| *v as u32).collect(),
             self.data.field.to_string(),
         )
     }
@@ -0,0 +1,240 @@
+use crate::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+/// Attempts to reach a peer before giving up on a message.
+const CONNECT_ATTEMPTS: usize = 10;
+/// Delay in ms before the second attempt, doubled at each attempt up to `MAX_CONNECT_DELAY`.
+const CONNECT_DELAY: u64 = 10;
+const MAX_CONNECT_DELAY: u64 = 1000;
+/// Time an attempt waits for the peer, a crashed host does not refuse the connection.
+const CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+/// Messages waiting to be written on the stream of a peer.
+const QUEUE_SIZE: usize = 1000;
+
+static CONNECTIONS: OnceLock<Connections> = OnceLock::new();
+
+/// Sends the messages of the process, either with a new connection per message or over one
+/// long-lived stream per peer fed by a send queue.
+pub struct Connections {
+    persistent: AtomicBool,
+    peers: std::sync::Mutex<HashMap<String, Sender<Frame>>>,
+    /// Peers the last attempts could not reach, they are tried once per message until they
+    /// are reached again.
+    failed: std::sync::Mutex<HashSet<String>>,
+}
+
+impl Connections {
+    pub fn get() -> &'static Connections {
+        CONNECTIONS.get_or_init(|| Connections {
+            persistent: AtomicBool::new(true),
+            peers: std::sync::Mutex::new(HashMap::new()),
+            failed: std::sync::Mutex::new(HashSet::new()),
+        })
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        self.persistent.load(Ordering::Relaxed)
+    }
+
+    /// Switching to a connection per message closes the streams once their queue is empty.
+    pub fn set_persistent(&self, persistent: bool) {
+        if !persistent {
+            self.peers.lock().unwrap().clear();
+        }
+        self.persistent.store(persistent, Ordering::Relaxed)
+    }
+
+    pub fn has_failed(&self, addr: &str) -> bool {
+        self.failed.lock().unwrap().contains(addr)
+    }
+
+    /// Tries to reach `addr` with an exponential backoff between the attempts, a peer that
+    /// failed is only tried once. Marks the peer as failed when it cannot be reached.
+    async fn connect(&self, addr: &str) -> std::io::Result<WriteStream> {
+        let attempts = if self.has_failed(addr) {
+            1
+        } else {
+            CONNECT_ATTEMPTS
+        };
+        let mut delay = CONNECT_DELAY;
+        let mut attempt = 1;
+        loop {
+            let error = match tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(addr)).await
+            {
+                Ok(Ok(stream)) => {
+                    self.failed.lock().unwrap().remove(addr);
+                    // Small messages follow each other on the stream, they must not wait for
+                    // the ack of the previous ones.
+                    stream.set_nodelay(true)?;
+                    return match Tls::get() {
+                        Some(tls) => tls.connect(addr, stream).await,
+                        None => Ok(Box::new(stream)),
+                    };
+                },
+                Ok(Err(e)) => e,
+                Err(_) => std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!("Connecting to {addr} timed out"),
+                ),
+            };
+            if attempt == attempts {
+                self.failed.lock().unwrap().insert(addr.to_string());
+                return Err(error);
+            }
+            attempt += 1;
+            tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+            delay = (2 * delay).min(MAX_CONNECT_DELAY);
+        }
+    }
+
+    /// Fails when `addr` cannot be reached. With persistent streams, the message is queued and
+    /// only the messages sent once the writer of the stream gave up fail. The next ones try
+    /// the failed peer once before being queued on a new stream.
+    pub async fn send(&self, addr: &str, frame: Frame) -> std::io::Result<()> {
+        if !self.is_persistent() {
+            let mut stream = self.connect(addr).await?;
+            return write_frame(&mut stream, &frame).await;
+        }
+        let queued = self.peers.lock().unwrap().get(addr).cloned();
+        let queue = match queued {
+            Some(queue) => queue,
+            None => {
+                let stream = match self.has_failed(addr) {
+                    true => Some(self.connect(addr).await?),
+                    false => None,
+                };
+                self.peers
+                    .lock()
+                    .unwrap()
+                    .entry(addr.to_string())
+                    .or_insert_with(|| Self::open(addr.to_string(), stream))
+                    .clone()
+            },
+        };
+        queue.send(frame).await.map_err(|_| {
+            self.forget(addr);
+            std::io::Error::new(
+                std::io::ErrorKind::NotConnected,
+                format!("Connection to {addr} closed"),
+            )
+        })
+    }
+
+    /// Spawns the writer of the stream to `addr`, starting with `stream` when it is given,
+    /// reconnecting and writing the message again when the stream fails. It drops its queue and
+    /// leaves the peers when `addr` cannot be reached anymore.
+    fn open(addr: String, mut stream: Option<WriteStream>) -> Sender<Frame> {
+        let (sender, mut receiver) = channel::<Frame>(QUEUE_SIZE);
+        tokio::spawn(async move {
+            while let Some(frame) = receiver.recv().await {
+                if let Err(e) = Self::write(&addr, &mut stream, &frame).await {
+                    eprintln!("Connection to {addr} lost: {e}");
+                    receiver.close();
+                    Self::get().forget(&addr);
+                    return;
+                }
+            }
+        });
+        sender
+    }
+
+    async fn write(
+        addr: &str,
+        stream: &mut Option<WriteStream>,
+        frame: &Frame,
+    ) -> std::io::Result<()> {
+        let mut attempt = 0;
+        loop {
+            if stream.is_none() {
+                *stream = Some(Self::get().connect(addr).await?);
+            }
+            match write_frame(stream.as_mut().unwrap(), frame).await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt + 1 == CONNECT_ATTEMPTS => return Err(e),
+                Err(_) => attempt += 1,
+            }
+            *stream = None;
+        }
+    }
+
+    /// Removes the queue of `addr` once its writer stopped.
+    fn forget(&self, addr: &str) {
+        let mut peers = self.peers.lock().unwrap();
+        if peers.get(addr).is_some_and(Sender::is_closed) {
+            peers.remove(addr);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn free_addr() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().to_string()
+    }
+
+    fn frame(i: u8) -> Frame {
+        Frame::new(CommandCode::PROTOCOL.into(), &[i; 3])
+    }
+
+    #[tokio::test]
+    async fn queued_frames_share_one_stream() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        for i in 0..10 {
+            Connections::get().send(&addr, frame(i)).await.unwrap();
+        }
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut reader = FrameReader::new(stream);
+        for i in 0..10 {
+            assert_eq!(reader.next().await, Some(frame(i)));
+        }
+    }
+
+    #[tokio::test]
+    async fn closed_streams_are_reopened() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        Connections::get().send(&addr, frame(0)).await.unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut reader = FrameReader::new(stream);
+        assert_eq!(reader.next().await, Some(frame(0)));
+        drop(reader);
+        // The writer only notices the closed stream when writing on it, the frames written
+        // until then are lost.
+        let accept = tokio::spawn(async move { listener.accept().await.unwrap().0 });
+        for i in 1..=u8::MAX {
+            Connections::get().send(&addr, frame(i)).await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            if accept.is_finished() {
+                break;
+            }
+        }
+        assert!(accept.is_finished());
+        let mut reader = FrameReader::new(accept.await.unwrap());
+        assert!(reader.next().await.is_some());
+    }
+
+    #[tokio::test]
+    async fn unreachable_peers_fail_at_once() {
+        let addr = free_addr().await;
+        // The frame is queued, the writer then gives up and leaves the peers.
+        Connections::get().send(&addr, frame(0)).await.unwrap();
+        while Connections::get().peers.lock().unwrap().contains_key(&addr) {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        assert!(Connections::get().has_failed(&addr));
+        // The failed peer is tried once, the frame is not queued.
+        let timer = Instant::now();
+        assert!(Connections::get().send(&addr, frame(1)).await.is_err());
+        assert!(timer.elapsed() < std::time::Duration::from_millis(CONNECT_DELAY * 10));
+        let listener = TcpListener::bind(&addr).await.unwrap();
+        Connections::get().send(&addr, frame(2)).await.unwrap();
+        assert!(!Connections::get().has_failed(&addr));
+        let mut reader = FrameReader::new(listener.accept().await.unwrap().0);
+        assert_eq!(reader.next().await, Some(frame(2)));
+    }
+}
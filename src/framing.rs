@@ -0,0 +1,214 @@
+use crate::*;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// Header of a frame: the length of the payload as a u32 and the type of the message.
+pub const FRAME_HEADER_SIZE: usize = 5;
+/// Frames announcing a larger payload close the stream.
+pub const MAX_FRAME_SIZE: usize = 1 << 26;
+/// Bytes read from the stream at once.
+const READ_SIZE: usize = 1 << 16;
+
+/// A message of a stream, `kind` is its `CommandCode` or its `InterfaceCode`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub kind: u8,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(kind: u8, payload: Bytes<'_>) -> Frame {
+        Frame {
+            kind,
+            payload: payload.to_vec(),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        encode_frame(self.kind, &self.payload)
+    }
+
+    /// Size of the frame on the stream.
+    pub fn encoded_len(&self) -> usize {
+        FRAME_HEADER_SIZE + self.payload.len()
+    }
+}
+
+pub fn encode_frame(kind: u8, payload: Bytes<'_>) -> Vec<u8> {
+    let mut buf = vec![0; FRAME_HEADER_SIZE + payload.len()];
+    LittleEndian::write_u32(&mut buf, payload.len() as u32);
+    buf[4] = kind;
+    buf[FRAME_HEADER_SIZE..].copy_from_slice(payload);
+    buf
+}
+
+/// Gathers the bytes read from a stream into frames, whatever the boundaries of the reads.
+#[derive(Default)]
+pub struct FrameDecoder {
+    buf: Vec<u8>,
+}
+
+impl FrameDecoder {
+    pub fn new() -> FrameDecoder {
+        FrameDecoder::default()
+    }
+
+    pub fn extend(&mut self, bytes: Bytes<'_>) {
+        self.buf.extend_from_slice(bytes)
+    }
+
+    /// The next complete frame, `None` while its bytes did not all arrive.
+    pub fn next_frame(&mut self) -> Result<Option<Frame>, &'static str> {
+        if self.buf.len() < FRAME_HEADER_SIZE {
+            return Ok(None);
+        }
+        let len = LittleEndian::read_u32(&self.buf) as usize;
+        if len > MAX_FRAME_SIZE {
+            return Err("Frame too large");
+        }
+        if self.buf.len() < FRAME_HEADER_SIZE + len {
+            return Ok(None);
+        }
+        let frame = Frame::new(
+            self.buf[4],
+            &self.buf[FRAME_HEADER_SIZE..FRAME_HEADER_SIZE + len],
+        );
+        self.buf.drain(..FRAME_HEADER_SIZE + len);
+        Ok(Some(frame))
+    }
+}
+
+/// Flushed, as a TLS stream buffers what it encrypts.
+pub async fn write_frame<W: AsyncWrite + Unpin>(
+    stream: &mut W,
+    frame: &Frame,
+) -> std::io::Result<()> {
+    stream.write_all(&frame.encode()).await?;
+    stream.flush().await
+}
+
+/// Reads the frames of a stream.
+pub struct FrameReader<R> {
+    stream: R,
+    decoder: FrameDecoder,
+    buf: Vec<u8>,
+}
+
+impl<R: AsyncRead + Unpin> FrameReader<R> {
+    pub fn new(stream: R) -> FrameReader<R> {
+        FrameReader {
+            stream,
+            decoder: FrameDecoder::new(),
+            buf: vec![0; READ_SIZE],
+        }
+    }
+
+    /// The next frame, `None` once the stream is closed or sent an invalid frame.
+    pub async fn next(&mut self) -> Option<Frame> {
+        loop {
+            match self.decoder.next_frame() {
+                Ok(Some(frame)) => return Some(frame),
+                Ok(None) => (),
+                Err(e) => {
+                    eprintln!("{e}");
+                    return None;
+                },
+            }
+            match self.stream.read(&mut self.buf).await {
+                Ok(0) | Err(_) => return None,
+                Ok(n) => self.decoder.extend(&self.buf[..n]),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frames with a `PROTOCOL` payload of `size` bytes.
+    fn frames(size: usize) -> Vec<Frame> {
+        vec![
+            Frame::new(CommandCode::SETUP.into(), &[1, 2, 3]),
+            Frame::new(CommandCode::STOP.into(), &[]),
+            Frame::new(
+                CommandCode::PROTOCOL.into(),
+                &(0..size).map(|i| i as u8).collect::<Vec<u8>>(),
+            ),
+            Frame::new(InterfaceCode::OUTPUT.into(), &[0; 7]),
+        ]
+    }
+
+    fn stream(size: usize) -> Vec<u8> {
+        frames(size)
+            .iter()
+            .flat_map(|frame| frame.encode())
+            .collect()
+    }
+
+    fn decode(chunks: &[&[u8]]) -> Vec<Frame> {
+        let mut decoder = FrameDecoder::new();
+        let mut res = Vec::new();
+        for chunk in chunks {
+            decoder.extend(chunk);
+            while let Some(frame) = decoder.next_frame().unwrap() {
+                res.push(frame)
+            }
+        }
+        res
+    }
+
+    #[test]
+    fn merged_frames_are_split() {
+        assert_eq!(decode(&[&stream(30_000)]), frames(30_000));
+    }
+
+    #[test]
+    fn frames_are_split_at_any_boundary() {
+        let stream = stream(300);
+        for cut in 0..=stream.len() {
+            assert_eq!(decode(&[&stream[..cut], &stream[cut..]]), frames(300));
+        }
+    }
+
+    #[test]
+    fn frames_are_read_byte_by_byte() {
+        let stream = stream(30_000);
+        let chunks = stream.chunks(1).collect::<Vec<_>>();
+        assert_eq!(decode(&chunks), frames(30_000));
+    }
+
+    #[test]
+    fn frames_are_read_in_uneven_chunks() {
+        let stream = stream(30_000);
+        for size in [2, 3, 5, 7, 1000, 4096] {
+            let chunks = stream.chunks(size).collect::<Vec<_>>();
+            assert_eq!(decode(&chunks), frames(30_000));
+        }
+    }
+
+    #[test]
+    fn too_large_frames_are_rejected() {
+        let mut decoder = FrameDecoder::new();
+        let mut header = [0; FRAME_HEADER_SIZE];
+        LittleEndian::write_u32(&mut header, MAX_FRAME_SIZE as u32 + 1);
+        decoder.extend(&header);
+        assert!(decoder.next_frame().is_err());
+    }
+
+    #[tokio::test]
+    async fn reader_gets_frames_written_in_pieces() {
+        let (mut client, server) = tokio::io::duplex(64);
+        let stream = stream(30_000);
+        tokio::spawn(async move {
+            for chunk in stream.chunks(13) {
+                client.write_all(chunk).await.unwrap();
+            }
+        });
+        let mut reader = FrameReader::new(server);
+        let mut res = Vec::new();
+        while let Some(frame) = reader.next().await {
+            res.push(frame)
+        }
+        assert_eq!(res, frames(30_000));
+    }
+}
@@ -0,0 +1,517 @@
+use crate::*;
+
+/// Version of the encoding of the messages, written in front of each of them. Messages of
+/// another version are rejected.
+pub const MESSAGE_VERSION: u8 = 1;
+
+/// A value written in the messages.
+pub trait Wire: Sized {
+    fn write(&self, buf: &mut Vec<u8>);
+
+    /// Reads the value at the start of `bytes` and advances it.
+    fn read(bytes: &mut Bytes<'_>) -> Result<Self, &'static str>;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.write(&mut buf);
+        buf
+    }
+
+    /// Reads a value taking all of `bytes`.
+    fn from_bytes(mut bytes: Bytes<'_>) -> Result<Self, &'static str> {
+        let res = Self::read(&mut bytes)?;
+        if !bytes.is_empty() {
+            return Err("Trailing bytes in message");
+        }
+        Ok(res)
+    }
+}
+
+fn take<'a>(bytes: &mut Bytes<'a>, n: usize) -> Result<Bytes<'a>, &'static str> {
+    if bytes.len() < n {
+        return Err("Truncated message");
+    }
+    let (res, rest) = bytes.split_at(n);
+    *bytes = rest;
+    Ok(res)
+}
+
+macro_rules! wire_int {
+    ($($t:ty),*) => {
+        $(impl Wire for $t {
+            fn write(&self, buf: &mut Vec<u8>) {
+                buf.extend_from_slice(&self.to_le_bytes())
+            }
+
+            fn read(bytes: &mut Bytes<'_>) -> Result<Self, &'static str> {
+                let bytes = take(bytes, std::mem::size_of::<$t>())?;
+                Ok(<$t>::from_le_bytes(bytes.try_into().unwrap()))
+            }
+        })*
+    };
+}
+
+wire_int!(u8, u16, u32, u64, u128);
+
+/// Enums declared with `as_number!` over a `u8`.
+macro_rules! wire_enum {
+    ($($t:ty),*) => {
+        $(impl Wire for $t {
+            fn write(&self, buf: &mut Vec<u8>) {
+                buf.push((*self).into())
+            }
+
+            fn read(bytes: &mut Bytes<'_>) -> Result<Self, &'static str> {
+                <$t>::from_number(u8::read(bytes)?).ok_or("Invalid enum value")
+            }
+        })*
+    };
+}
+
+wire_enum!(ByzComp, ErrorCode, Distribution);
+
+impl Wire for bool {
+    fn write(&self, buf: &mut Vec<u8>) {
+        buf.push(*self as u8)
+    }
+
+    fn read(bytes: &mut Bytes<'_>) -> Result<Self, &'static str> {
+        match u8::read(bytes)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err("Invalid bool"),
+        }
+    }
+}
+
+impl<T: Wire> Wire for Vec<T> {
+    fn write(&self, buf: &mut Vec<u8>) {
+        (self.len() as u32).write(buf);
+        self.iter().for_each(|elt| elt.write(buf))
+    }
+
+    fn read(bytes: &mut Bytes<'_>) -> Result<Self, &'static str> {
+        let len = u32::read(bytes)? as usize;
+        if len > bytes.len() {
+            return Err("Truncated message");
+        }
+        (0..len).map(|_| T::read(bytes)).collect()
+    }
+}
+
+impl<T: Wire> Wire for Option<T> {
+    fn write(&self, buf: &mut Vec<u8>) {
+        self.is_some().write(buf);
+        if let Some(value) = self {
+            value.write(buf)
+        }
+    }
+
+    fn read(bytes: &mut Bytes<'_>) -> Result<Self, &'static str> {
+        Ok(match bool::read(bytes)? {
+            true => Some(T::read(bytes)?),
+            false => None,
+        })
+    }
+}
+
+macro_rules! wire_tuple {
+    ($(($($t:ident),*)),*) => {
+        $(#[allow(non_snake_case)]
+        impl<$($t: Wire),*> Wire for ($($t,)*) {
+            fn write(&self, buf: &mut Vec<u8>) {
+                let ($($t,)*) = self;
+                $($t.write(buf);)*
+            }
+
+            fn read(bytes: &mut Bytes<'_>) -> Result<Self, &'static str> {
+                Ok(($($t::read(bytes)?,)*))
+            }
+        })*
+    };
+}
+
+wire_tuple!((A, B), (A, B, C));
+
+impl<const N: usize> Wire for [u8; N] {
+    fn write(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self)
+    }
+
+    fn read(bytes: &mut Bytes<'_>) -> Result<Self, &'static str> {
+        Ok(take(bytes, N)?.try_into().unwrap())
+    }
+}
+
+impl Wire for String {
+    fn write(&self, buf: &mut Vec<u8>) {
+        (self.len() as u32).write(buf);
+        buf.extend_from_slice(self.as_bytes())
+    }
+
+    fn read(bytes: &mut Bytes<'_>) -> Result<Self, &'static str> {
+        let len = u32::read(bytes)? as usize;
+        String::from_utf8(take(bytes, len)?.to_vec()).map_err(|_| "Invalid string")
+    }
+}
+
+/// A struct whose fields are written one after the other.
+#[macro_export]
+macro_rules! wire_struct {
+    ($(#[$meta:meta])* struct $name:ident { $($(#[$field_meta:meta])* $field:ident: $t:ty),* $(,)? }) => {
+        $(#[$meta])*
+        pub struct $name {
+            $($(#[$field_meta])* pub $field: $t),*
+        }
+
+        impl $crate::Wire for $name {
+            fn write(&self, buf: &mut Vec<u8>) {
+                $($crate::Wire::write(&self.$field, buf);)*
+            }
+
+            fn read(bytes: &mut $crate::Bytes<'_>) -> Result<Self, &'static str> {
+                Ok($name {
+                    $($field: $crate::Wire::read(bytes)?),*
+                })
+            }
+        }
+    };
+}
+
+/// An enum with a variant for each value of the code `$code`, written as the code followed by
+/// the fields of the variant. The protocols of the nodes give their messages with it.
+#[macro_export]
+macro_rules! payloads {
+    ($(#[$meta:meta])* enum $name:ident: $code:ident {
+        $($(#[$variant_meta:meta])* $c:ident => $variant:ident { $($field:ident: $t:ty),* $(,)? }),* $(,)?
+    }) => {
+        $(#[$meta])*
+        pub enum $name {
+            $($(#[$variant_meta])* $variant { $($field: $t),* }),*
+        }
+
+        impl $crate::Wire for $name {
+            fn write(&self, buf: &mut Vec<u8>) {
+                match self {
+                    $($name::$variant { $($field),* } => {
+                        buf.push($code::$c.into());
+                        $($crate::Wire::write($field, buf);)*
+                    }),*
+                }
+            }
+
+            fn read(bytes: &mut $crate::Bytes<'_>) -> Result<Self, &'static str> {
+                let code = <u8 as $crate::Wire>::read(bytes)?;
+                Ok(match <$code as $crate::FromNumber<u8>>::from_number(code)
+                    .ok_or("Unknown message")?
+                {
+                    $($code::$c => $name::$variant {
+                        $($field: $crate::Wire::read(bytes)?),*
+                    }),*
+                })
+            }
+        }
+    };
+}
+
+wire_struct!(
+    /// Parameters of a sharing, sent by the interface to every node.
+    #[derive(Debug, Clone, PartialEq)]
+    struct Setup {
+        /// Round of the interface at the sharing.
+        round: u32,
+        dealer: u16,
+        byz_comp: ByzComp,
+        d: u16,
+        f: u16,
+        victims: u16,
+        decoding: bool,
+        /// Percentages of the actions of a `ByzComp::Mixed` node.
+        probas: [u8; 3],
+        connect_per_message: bool,
+        protocol: String,
+        n: u16,
+        /// Addresses of the nodes, empty when the network did not change.
+        network: Vec<String>,
+        /// Certificates of `network` when the streams are TLS, empty otherwise.
+        certs: Vec<Vec<u8>>,
+        /// Nodes known to have crashed, the others do not wait for them.
+        crashed: Vec<u16>,
+        emulation: NetworkEmulation,
+        costs: Costs,
+    }
+);
+
+wire_struct!(
+    /// Secret reconstructed by a node decoding the shares it received.
+    #[derive(Debug, Clone, PartialEq)]
+    struct Decoded {
+        secret: u128,
+        /// Nodes whose shares were corrected.
+        wrong: Vec<u16>,
+    }
+);
+
+/// Messages sent over the streams, as frames whose kind is their code.
+pub trait Message: Sized {
+    fn to_frame(&self) -> Frame;
+
+    fn from_frame(frame: &Frame) -> Result<Self, &'static str>;
+}
+
+/// An enum with a variant for each value of the code `$code`, encoded as the version followed
+/// by the fields of the variant.
+macro_rules! messages {
+    ($(#[$meta:meta])* enum $name:ident: $code:ident {
+        $($(#[$variant_meta:meta])* $c:ident => $variant:ident { $($field:ident: $t:ty),* $(,)? }),* $(,)?
+    }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq)]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant { $($field: $t),* }),*
+        }
+
+        impl Message for $name {
+            fn to_frame(&self) -> Frame {
+                let mut payload = vec![MESSAGE_VERSION];
+                let code = match self {
+                    $($name::$variant { $($field),* } => {
+                        $($field.write(&mut payload);)*
+                        $code::$c
+                    }),*
+                };
+                Frame {
+                    kind: code.into(),
+                    payload,
+                }
+            }
+
+            fn from_frame(frame: &Frame) -> Result<Self, &'static str> {
+                let mut bytes: Bytes<'_> = &frame.payload;
+                if u8::read(&mut bytes)? != MESSAGE_VERSION {
+                    return Err("Unsupported message version");
+                }
+                let msg = match $code::from_number(frame.kind).ok_or("Unknown message")? {
+                    $($code::$c => $name::$variant {
+                        $($field: Wire::read(&mut bytes)?),*
+                    }),*
+                };
+                if !bytes.is_empty() {
+                    return Err("Trailing bytes in message");
+                }
+                Ok(msg)
+            }
+        }
+    };
+}
+
+messages!(
+    /// Messages received by the nodes. The `SETUP` is the large one, sent once per sharing.
+    #[allow(clippy::large_enum_variant)]
+    enum NodeMessage: CommandCode {
+        DEALTHIS => DealThis { secret: u128 },
+        /// `bytes` starts with the code of the message in the protocol `protocol`, its index in
+        /// the protocols of the nodes.
+        PROTOCOL => Protocol { protocol: u8, bytes: Vec<u8> },
+        SETUP => Setup { setup: Setup },
+        KEY => Key { index: u16, key: Vec<u8> },
+        /// `round` is the round of the interface, it tags the shares revealed by the nodes.
+        /// `corrupt` is the behaviour an adaptively corrupted node takes before revealing its
+        /// share.
+        RECONSTRUCT => Reconstruct { round: u32, corrupt: Option<ByzComp> },
+        /// Ends the reconstruction of `round`, it can arrive after the next `SETUP`.
+        STOP => Stop { round: u32 },
+        CORRUPT => Corrupt { byz_comp: ByzComp },
+        /// The nodes `nodes` crashed, a node among them crashes on receiving it.
+        CRASH => Crash { nodes: Vec<u16> },
+    }
+);
+
+messages!(
+    /// Messages received by the interface.
+    enum InterfaceMessage: InterfaceCode {
+        /// `addr` is the address the node advertises, the other nodes reach it there.
+        CONNECT => Connect { addr: String },
+        INTERRUPT => Interrupt {},
+        /// `round` is the round of the interface the node was set up or reconstructing for,
+        /// the outputs of an ended round are dropped. `index` is the index of the node.
+        OUTPUT => Output { round: u32, index: u16, result: ResultFields },
+        FROMFILE => FromFile { path: String },
+    }
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> Setup {
+        Setup {
+            round: 8,
+            dealer: 3,
+            byz_comp: ByzComp::Mixed,
+            d: 20,
+            f: 10,
+            victims: 4,
+            decoding: true,
+            probas: [50, 30, 20],
+            connect_per_message: false,
+            protocol: "avss_simpl".to_string(),
+            n: 2,
+            network: vec!["127.0.0.1:1234".to_string(), "127.0.0.1:5678".to_string()],
+            certs: vec![vec![1, 2], vec![3]],
+            crashed: vec![1],
+            emulation: NetworkEmulation {
+                default: Link {
+                    latency: 20,
+                    jitter: 5,
+                    distribution: Distribution::Normal,
+                    bandwidth: 1000,
+                    loss: 1,
+                },
+                groups: vec![vec![0], vec![1]],
+                links: vec![vec![Link::default(); 2]; 2],
+                partitions: vec![Partition {
+                    at: 100,
+                    groups: vec![vec![0]],
+                    duration: 300,
+                }],
+            },
+            costs: Costs {
+                verify: 900,
+                decode: 2000,
+                ..Costs::default()
+            },
+        }
+    }
+
+    fn result() -> ResultFields {
+        let mut result = ResultFields::new();
+        result.set(TypeResultField::Verify, 12);
+        result.set(TypeResultField::Reconstruction, u128::MAX);
+        result.set_code(ErrorCode::MissingShare);
+        result.set_decoded(u128::MAX - 1, vec![3, 17]);
+        result
+    }
+
+    fn node_messages() -> Vec<NodeMessage> {
+        vec![
+            NodeMessage::DealThis { secret: u128::MAX },
+            NodeMessage::Protocol {
+                protocol: 1,
+                bytes: (0..5000).map(|i| i as u8).collect(),
+            },
+            NodeMessage::Setup { setup: setup() },
+            NodeMessage::Key {
+                index: 7,
+                key: vec![0, 1, 2, 0, 0],
+            },
+            NodeMessage::Reconstruct {
+                round: 9,
+                corrupt: None,
+            },
+            NodeMessage::Reconstruct {
+                round: 10,
+                corrupt: Some(ByzComp::WrongShares),
+            },
+            NodeMessage::Stop { round: 6 },
+            NodeMessage::Corrupt {
+                byz_comp: ByzComp::WrongShares,
+            },
+            NodeMessage::Crash { nodes: vec![2, 5] },
+        ]
+    }
+
+    fn interface_messages() -> Vec<InterfaceMessage> {
+        vec![
+            InterfaceMessage::Connect {
+                addr: "127.0.0.2:18802".to_string(),
+            },
+            InterfaceMessage::Interrupt {},
+            InterfaceMessage::Output {
+                round: 11,
+                index: 4,
+                result: result(),
+            },
+            InterfaceMessage::FromFile {
+                path: "configs/test.json".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn node_messages_round_trip() {
+        for msg in node_messages() {
+            assert_eq!(NodeMessage::from_frame(&msg.to_frame()), Ok(msg));
+        }
+    }
+
+    #[test]
+    fn interface_messages_round_trip() {
+        for msg in interface_messages() {
+            assert_eq!(InterfaceMessage::from_frame(&msg.to_frame()), Ok(msg));
+        }
+    }
+
+    #[test]
+    fn messages_round_trip_through_the_decoder() {
+        let mut decoder = FrameDecoder::new();
+        for msg in node_messages() {
+            decoder.extend(&msg.to_frame().encode());
+        }
+        for msg in node_messages() {
+            let frame = decoder.next_frame().unwrap().unwrap();
+            assert_eq!(NodeMessage::from_frame(&frame), Ok(msg));
+        }
+    }
+
+    #[test]
+    fn setup_holds_a_large_committee() {
+        let network = (0..1000)
+            .map(|i| format!("127.0.0.1:{}", 10000 + i))
+            .collect::<Vec<_>>();
+        let msg = NodeMessage::Setup {
+            setup: Setup {
+                n: network.len() as u16,
+                network,
+                ..setup()
+            },
+        };
+        let mut decoder = FrameDecoder::new();
+        decoder.extend(&msg.to_frame().encode());
+        let frame = decoder.next_frame().unwrap().unwrap();
+        assert_eq!(NodeMessage::from_frame(&frame), Ok(msg));
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let mut frame = NodeMessage::Stop { round: 0 }.to_frame();
+        frame.payload[0] = MESSAGE_VERSION + 1;
+        assert!(NodeMessage::from_frame(&frame).is_err());
+    }
+
+    #[test]
+    fn truncated_and_longer_messages_are_rejected() {
+        for msg in node_messages() {
+            let frame = msg.to_frame();
+            for len in 0..frame.payload.len().min(100) {
+                let truncated = Frame::new(frame.kind, &frame.payload[..len]);
+                assert!(NodeMessage::from_frame(&truncated).is_err());
+            }
+            let mut longer = frame.clone();
+            longer.payload.push(0);
+            assert!(NodeMessage::from_frame(&longer).is_err());
+        }
+    }
+
+    #[test]
+    fn unknown_codes_and_values_are_rejected() {
+        let frame = Frame::new(200, &[MESSAGE_VERSION]);
+        assert!(NodeMessage::from_frame(&frame).is_err());
+        let mut frame = NodeMessage::Corrupt {
+            byz_comp: ByzComp::Sleeper,
+        }
+        .to_frame();
+        frame.payload[1] = 200;
+        assert!(NodeMessage::from_frame(&frame).is_err());
+    }
+}
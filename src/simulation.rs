@@ -0,0 +1,108 @@
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use std::cell::RefCell;
+
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+/// Generator of the current thread, drawing the random choices of the nodes and of the
+/// emulated links. A simulation seeds it, its runtime runs every task on a single thread.
+#[derive(Clone, Copy)]
+pub struct SimRng;
+
+pub fn rng() -> SimRng {
+    SimRng
+}
+
+impl RngCore for SimRng {
+    fn next_u32(&mut self) -> u32 {
+        RNG.with(|rng| rng.borrow_mut().next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        RNG.with(|rng| rng.borrow_mut().next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        RNG.with(|rng| rng.borrow_mut().fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        RNG.with(|rng| rng.borrow_mut().try_fill_bytes(dest))
+    }
+}
+
+/// The runtime of a process. With a seed it is a simulation: every task runs on the current
+/// thread, whose generator is seeded, and the clock is virtual. It only moves forward when all
+/// the tasks wait, to the next timer, so a run only depends on the seed.
+pub fn runtime(seed: Option<u64>) -> tokio::runtime::Runtime {
+    let mut builder = match seed {
+        Some(seed) => {
+            RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+            let mut builder = tokio::runtime::Builder::new_current_thread();
+            builder.start_paused(true);
+            builder
+        },
+        None => tokio::runtime::Builder::new_multi_thread(),
+    };
+    builder
+        .enable_all()
+        .build()
+        .expect("Failed to build the runtime")
+}
+
+/// The computations a node runs at once, one per core of the machine. A simulated node runs
+/// one at a time, as the modeled costs of its operations assume.
+pub fn cores() -> usize {
+    match tokio::runtime::Handle::current().runtime_flavor() {
+        tokio::runtime::RuntimeFlavor::CurrentThread => 1,
+        _ => std::thread::available_parallelism().map_or(1, |cores| cores.get()),
+    }
+}
+
+/// Runs the CPU-bound `work` on the blocking pool, the tasks of the process go on meanwhile. It
+/// runs in place with a single core: the clock of a simulation would move forward while the
+/// pool works, and a machine with one core only pays the switches between the threads.
+pub async fn compute<T: Send + 'static>(work: impl FnOnce() -> T + Send + 'static) -> T {
+    if cores() == 1 {
+        return work();
+    }
+    tokio::task::spawn_blocking(work)
+        .await
+        .expect("A computation panicked")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn seeded_runtimes_draw_the_same_numbers() {
+        let draw = || {
+            runtime(Some(7)).block_on(async {
+                let start = crate::Instant::now();
+                tokio::time::sleep(std::time::Duration::from_secs(3600)).await;
+                let numbers = (0..10).map(|_| rng().gen::<u64>()).collect::<Vec<_>>();
+                (numbers, start.elapsed())
+            })
+        };
+        let (numbers, elapsed) = draw();
+        assert_eq!(draw(), (numbers, elapsed));
+        assert_eq!(elapsed, std::time::Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn computations_do_not_move_the_simulated_clock() {
+        let elapsed = runtime(Some(7)).block_on(async {
+            let start = crate::Instant::now();
+            tokio::spawn(tokio::time::sleep(std::time::Duration::from_secs(3600)));
+            let work = || std::thread::sleep(std::time::Duration::from_millis(10));
+            compute(work).await;
+            start.elapsed()
+        });
+        assert_eq!(elapsed, std::time::Duration::ZERO);
+        assert_eq!(runtime(None).block_on(compute(|| 1 + 1)), 2);
+    }
+}
@@ -0,0 +1,319 @@
+use crate::*;
+use std::sync::OnceLock;
+use std::time::SystemTime;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::rustls::client::{ServerCertVerified, ServerCertVerifier, ServerName};
+use tokio_rustls::rustls::server::{ClientCertVerified, ClientCertVerifier};
+use tokio_rustls::rustls::{
+    Certificate, ClientConfig, DistinguishedNames, Error, PrivateKey, ServerConfig,
+};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+static TLS: OnceLock<Tls> = OnceLock::new();
+
+/// Time a node waits for the certificate of an incoming stream to be pinned, a peer can
+/// connect before the `SETUP` giving its certificate arrives.
+const ADMISSION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Followed by the certificate of the interface in hex, the streams of a node are TLS.
+pub static INTERFACE_CERT_FLAG: &str = "--interface-cert=";
+
+/// Stream read by a listener, TLS or plain TCP.
+pub type ReadStream = Box<dyn AsyncRead + Unpin + Send>;
+/// Stream written by `Connections`, TLS or plain TCP.
+pub type WriteStream = Box<dyn AsyncWrite + Unpin + Send>;
+
+/// Mutually authenticated TLS streams. The process holds a certificate self-signed with its key
+/// and only accepts the certificates it pinned, exactly, whatever their content. The handshake
+/// proves the peer holds the key of its certificate.
+pub struct Tls {
+    cert: Vec<u8>,
+    key: Vec<u8>,
+    pins: std::sync::RwLock<HashMap<String, Vec<u8>>>,
+    /// Notified when a certificate is pinned.
+    pinned: Notify,
+    /// Whether the streams of unpinned certificates are read, the interface admits the nodes
+    /// before it knows them and pins the certificate of the stream that sends their `CONNECT`.
+    open: bool,
+    acceptor: TlsAcceptor,
+}
+
+impl Tls {
+    /// `key` is an Ed25519 key pair in PKCS#8.
+    pub fn new(key: &[u8], open: bool) -> Tls {
+        let mut params = rcgen::CertificateParams::new(vec!["node".to_string()]);
+        params.alg = &rcgen::PKCS_ED25519;
+        params.key_pair = Some(rcgen::KeyPair::from_der(key).expect("Invalid key"));
+        let cert = rcgen::Certificate::from_params(params).expect("Failed to certify the key");
+        let cert = cert
+            .serialize_der()
+            .expect("Failed to serialize the certificate");
+        // A server accepts any certificate at the handshake, as a client considers the
+        // handshake over before the server checks its certificate, then `accept` admits it.
+        let config = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_client_cert_verifier(Arc::new(AnyClient))
+            .with_single_cert(vec![Certificate(cert.clone())], PrivateKey(key.to_vec()))
+            .expect("Invalid certificate");
+        Tls {
+            cert,
+            key: key.to_vec(),
+            pins: std::sync::RwLock::new(HashMap::new()),
+            pinned: Notify::new(),
+            open,
+            acceptor: TlsAcceptor::from(Arc::new(config)),
+        }
+    }
+
+    /// With a new key.
+    pub fn generate(open: bool) -> Tls {
+        let key = rcgen::KeyPair::generate(&rcgen::PKCS_ED25519).expect("Failed to construct key");
+        Tls::new(&key.serialize_der(), open)
+    }
+
+    /// The streams of the process are TLS from now on.
+    pub fn enable(tls: Tls) -> &'static Tls {
+        if TLS.set(tls).is_err() {
+            panic!("TLS is already enabled");
+        }
+        TLS.get().unwrap()
+    }
+
+    /// `None` when the streams are plain TCP.
+    pub fn get() -> Option<&'static Tls> {
+        TLS.get()
+    }
+
+    pub fn cert(&self) -> &[u8] {
+        &self.cert
+    }
+
+    pub fn pin(&self, addr: String, cert: Vec<u8>) {
+        self.pins.write().unwrap().insert(addr, cert);
+        self.pinned.notify_waiters();
+    }
+
+    pub fn pinned(&self, addr: &str) -> Option<Vec<u8>> {
+        self.pins.read().unwrap().get(addr).cloned()
+    }
+
+    /// Whether a stream with `cert` can send frames to the process.
+    pub fn admits(&self, cert: &[u8]) -> bool {
+        self.identify(cert).is_some()
+    }
+
+    /// The address `cert` is pinned for, the peer a stream presenting it speaks for. Its
+    /// frames claiming another identity are dropped by the receiver.
+    pub fn identify(&self, cert: &[u8]) -> Option<String> {
+        let pins = self.pins.read().unwrap();
+        pins.iter()
+            .find(|(_, pin)| *pin == cert)
+            .map(|(addr, _)| addr.clone())
+    }
+
+    /// Authenticates the stream to `addr`, which must present the certificate pinned for it.
+    pub async fn connect(&self, addr: &str, stream: TcpStream) -> std::io::Result<WriteStream> {
+        let pin = self.pinned(addr).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!("No certificate pinned for {addr}"),
+            )
+        })?;
+        let config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(Arc::new(PinnedServer(pin)))
+            .with_single_cert(
+                vec![Certificate(self.cert.clone())],
+                PrivateKey(self.key.clone()),
+            )
+            .expect("Invalid certificate");
+        // The verifier ignores the name, the address identifies the peer.
+        let name = ServerName::try_from("node").unwrap();
+        let stream = TlsConnector::from(Arc::new(config))
+            .connect(name, stream)
+            .await?;
+        Ok(Box::new(stream))
+    }
+
+    /// Authenticates an incoming stream, returns it with the certificate of the peer. Unless the
+    /// process is open, the certificate must be pinned within `ADMISSION_TIMEOUT`.
+    pub async fn accept(&self, stream: TcpStream) -> std::io::Result<(ReadStream, Vec<u8>)> {
+        let stream = self.acceptor.accept(stream).await?;
+        let cert = stream.get_ref().1.peer_certificates().unwrap()[0].0.clone();
+        if !self.open {
+            let admitted = async {
+                loop {
+                    let pinned = self.pinned.notified();
+                    if self.admits(&cert) {
+                        break;
+                    }
+                    pinned.await;
+                }
+            };
+            if tokio::time::timeout(ADMISSION_TIMEOUT, admitted)
+                .await
+                .is_err()
+            {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::PermissionDenied,
+                    "Unpinned certificate",
+                ));
+            }
+        }
+        Ok((Box::new(stream), cert))
+    }
+}
+
+/// Accepts an incoming stream, authenticated when TLS is enabled. Returns it with the
+/// certificate of the peer, `None` over plain TCP.
+pub async fn accept_stream(stream: TcpStream) -> std::io::Result<(ReadStream, Option<Vec<u8>>)> {
+    match Tls::get() {
+        Some(tls) => {
+            let (stream, cert) = tls.accept(stream).await?;
+            Ok((stream, Some(cert)))
+        },
+        None => Ok((Box::new(stream), None)),
+    }
+}
+
+pub fn to_hex(bytes: Bytes<'_>) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub fn from_hex(hex: &str) -> Result<Vec<u8>, &'static str> {
+    if !hex.is_ascii() || !hex.len().is_multiple_of(2) {
+        return Err("Invalid hex");
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| "Invalid hex"))
+        .collect()
+}
+
+/// Accepts the server presenting the pinned certificate, the handshake then checks it holds
+/// its key.
+struct PinnedServer(Vec<u8>);
+
+impl ServerCertVerifier for PinnedServer {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, Error> {
+        if end_entity.0 == self.0 {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(Error::General("Unpinned certificate".to_string()))
+        }
+    }
+
+    fn request_scts(&self) -> bool {
+        false
+    }
+}
+
+/// Accepts any client holding the key of its certificate, see `Tls::accept`.
+struct AnyClient;
+
+impl ClientCertVerifier for AnyClient {
+    fn client_auth_root_subjects(&self) -> Option<DistinguishedNames> {
+        Some(Vec::new())
+    }
+
+    fn verify_client_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _now: SystemTime,
+    ) -> Result<ClientCertVerified, Error> {
+        Ok(ClientCertVerified::assertion())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sends a frame from `client` to `server`, the client expecting `pin` at the address of the
+    /// server. Returns the certificate the server saw and the frame it read.
+    async fn exchange(
+        client: &Tls,
+        server: &Tls,
+        pin: Bytes<'_>,
+    ) -> std::io::Result<(Vec<u8>, Option<Frame>)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        client.pin(addr.clone(), pin.to_vec());
+        let accepting = async {
+            let (stream, cert) = server.accept(listener.accept().await?.0).await?;
+            Ok((cert, FrameReader::new(stream).next().await))
+        };
+        let connecting = async {
+            let mut stream = client
+                .connect(&addr, TcpStream::connect(&addr).await?)
+                .await?;
+            write_frame(&mut stream, &Frame::new(0, &[1, 2, 3])).await
+        };
+        let (accepted, connected) = tokio::join!(accepting, connecting);
+        connected.and(accepted)
+    }
+
+    #[tokio::test]
+    async fn pinned_peers_connect() {
+        let (client, server) = (Tls::generate(false), Tls::generate(false));
+        server.pin("client".to_string(), client.cert().to_vec());
+        let (cert, frame) = exchange(&client, &server, server.cert()).await.unwrap();
+        assert_eq!(cert, client.cert());
+        assert_eq!(frame, Some(Frame::new(0, &[1, 2, 3])));
+        assert_eq!(server.identify(&cert), Some("client".to_string()));
+        assert_eq!(server.identify(server.cert()), None);
+    }
+
+    /// A node restarting with its key connects again with the certificate pinned for it.
+    #[test]
+    fn the_certificate_only_depends_on_the_key() {
+        let key = rcgen::KeyPair::generate(&rcgen::PKCS_ED25519).unwrap();
+        let cert = Tls::new(&key.serialize_der(), false).cert().to_vec();
+        assert_eq!(Tls::new(&key.serialize_der(), true).cert(), cert);
+        assert_ne!(Tls::generate(false).cert(), cert);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unpinned_certificates_are_refused() {
+        let (client, server, other) = (
+            Tls::generate(false),
+            Tls::generate(false),
+            Tls::generate(false),
+        );
+        assert!(exchange(&client, &server, server.cert()).await.is_err());
+        server.pin("client".to_string(), client.cert().to_vec());
+        assert!(exchange(&client, &server, other.cert()).await.is_err());
+        // An open process reads the stream, the frames then tell whether it admits it.
+        let open = Tls::generate(true);
+        let (cert, frame) = exchange(&client, &open, open.cert()).await.unwrap();
+        assert!(frame.is_some() && !open.admits(&cert));
+    }
+
+    #[tokio::test]
+    async fn certificates_pinned_late_are_admitted() {
+        let (client, server) = (Tls::generate(false), Tls::generate(false));
+        let pinning = async {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            server.pin("client".to_string(), client.cert().to_vec());
+        };
+        let (exchanged, ()) = tokio::join!(exchange(&client, &server, server.cert()), pinning);
+        assert!(exchanged.is_ok());
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        let bytes = vec![0, 1, 0xab, 0xff];
+        assert_eq!(to_hex(&bytes), "0001abff");
+        assert_eq!(from_hex(&to_hex(&bytes)), Ok(bytes));
+        assert!(from_hex("abc").is_err() && from_hex("zz").is_err());
+    }
+}
@@ -0,0 +1,410 @@
+use crate::*;
+pub use async_trait::async_trait;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Host of the addresses of the nodes run as tasks of a single process.
+pub static MEMORY_HOST: &str = "memory";
+/// Frames waiting in the inbox of a task or of a node.
+pub const INBOX_SIZE: usize = 1000;
+
+/// How the frames of a node or of the interface reach the others, the protocols only send
+/// through it.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// Fails when `addr` cannot be reached.
+    async fn send(&self, addr: &str, frame: Frame) -> std::io::Result<()>;
+}
+
+fn not_connected(addr: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::NotConnected,
+        format!("{addr} is not reachable"),
+    )
+}
+
+/// The error of the frames to a peer known to have crashed, the crash was already reported.
+pub fn crashed(addr: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::ConnectionAborted,
+        format!("{addr} crashed"),
+    )
+}
+
+/// Streams between the processes, see `Connections`.
+pub struct TcpTransport;
+
+#[async_trait]
+impl Transport for TcpTransport {
+    async fn send(&self, addr: &str, frame: Frame) -> std::io::Result<()> {
+        Connections::get().send(addr, frame).await
+    }
+}
+
+/// Channels between the tasks of a single process, each reached at the address it registered.
+pub struct MemoryNetwork {
+    inboxes: std::sync::Mutex<HashMap<String, Sender<Frame>>>,
+    /// The frames sent so far, when the network is traced.
+    trace: Option<std::sync::Mutex<Vec<String>>>,
+    start: Instant,
+}
+
+impl Default for MemoryNetwork {
+    fn default() -> Self {
+        MemoryNetwork {
+            inboxes: std::sync::Mutex::new(HashMap::new()),
+            trace: None,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl MemoryNetwork {
+    /// A network recording the frames sent through it, see `trace`.
+    pub fn traced() -> MemoryNetwork {
+        MemoryNetwork {
+            trace: Some(std::sync::Mutex::new(Vec::new())),
+            ..MemoryNetwork::default()
+        }
+    }
+
+    /// A line per frame sent: its time in µs since the creation of the network, its
+    /// destination, its kind and its length. `None` when the network is not traced.
+    pub fn trace(&self) -> Option<Vec<String>> {
+        self.trace
+            .as_ref()
+            .map(|trace| trace.lock().unwrap().clone())
+    }
+
+    /// The inbox of `addr`, replacing the previous one.
+    pub fn register(&self, addr: &str) -> Receiver<Frame> {
+        let (sender, receiver) = channel(INBOX_SIZE);
+        self.inboxes
+            .lock()
+            .unwrap()
+            .insert(addr.to_string(), sender);
+        receiver
+    }
+
+    /// `addr` cannot be reached anymore, as a crashed process.
+    pub fn unregister(&self, addr: &str) {
+        self.inboxes.lock().unwrap().remove(addr);
+    }
+}
+
+#[async_trait]
+impl Transport for MemoryNetwork {
+    async fn send(&self, addr: &str, frame: Frame) -> std::io::Result<()> {
+        if let Some(trace) = &self.trace {
+            let at = self.start.elapsed().as_micros();
+            let line = format!("{at} {addr} {} {}", frame.kind, frame.encoded_len());
+            trace.lock().unwrap().push(line);
+        }
+        let inbox = self.inboxes.lock().unwrap().get(addr).cloned();
+        let inbox = inbox.ok_or_else(|| not_connected(addr))?;
+        inbox.send(frame).await.map_err(|_| not_connected(addr))
+    }
+}
+
+/// Emulated link to a peer. Its frames wait in order for their delivery time, a frame
+/// delayed by the jitter holds the next ones back as on a stream.
+struct EmulatedLink {
+    link: Link,
+    /// End of the transmission of the last frame, the next ones wait for the bandwidth.
+    busy_until: Instant,
+    /// Windows of the partitions separating the peer, with their heal.
+    cuts: Vec<(Instant, Option<Instant>)>,
+    delayed: Sender<(Instant, Frame)>,
+}
+
+/// What the emulated network does with a frame.
+enum Route {
+    Direct,
+    Lost,
+    Delayed(Sender<(Instant, Frame)>, Instant),
+}
+
+/// Frames and bytes of a node by `CommandCode`, as `(frames, bytes)`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Traffic {
+    pub sent: [(u64, u64); COMMAND_NAMES.len()],
+    pub received: [(u64, u64); COMMAND_NAMES.len()],
+}
+
+impl Traffic {
+    fn count(counts: &mut [(u64, u64)], frame: &Frame) {
+        if let Some((frames, bytes)) = counts.get_mut(frame.kind as usize) {
+            *frames += 1;
+            *bytes += frame.encoded_len() as u64;
+        }
+    }
+}
+
+/// The transport of a node, emulating the links to its peers over `inner`.
+pub struct EmulatedTransport {
+    inner: Arc<dyn Transport>,
+    links: std::sync::Mutex<HashMap<String, EmulatedLink>>,
+    /// Address of the interface, the frames sent to it are not counted in the traffic.
+    interface: String,
+    traffic: std::sync::Mutex<Traffic>,
+    /// Peers that crashed, reported by the interface or found by a failed send. Their frames
+    /// fail at once until `clear_failures`.
+    failed: Arc<std::sync::Mutex<HashSet<String>>>,
+    /// Whether the node crashed, it sends nothing anymore.
+    down: AtomicBool,
+}
+
+impl EmulatedTransport {
+    pub fn new(inner: Arc<dyn Transport>, interface: String) -> EmulatedTransport {
+        EmulatedTransport {
+            inner,
+            links: std::sync::Mutex::new(HashMap::new()),
+            interface,
+            traffic: std::sync::Mutex::new(Traffic::default()),
+            failed: Arc::new(std::sync::Mutex::new(HashSet::new())),
+            down: AtomicBool::new(false),
+        }
+    }
+
+    /// The frames to `addr` fail at once, the interface is never marked.
+    pub fn mark_failed(&self, addr: &str) {
+        if addr != self.interface {
+            self.failed.lock().unwrap().insert(addr.to_string());
+        }
+    }
+
+    /// The peers are tried again, at each setup.
+    pub fn clear_failures(&self) {
+        self.failed.lock().unwrap().clear();
+    }
+
+    pub fn has_failed(&self, addr: &str) -> bool {
+        self.failed.lock().unwrap().contains(addr)
+    }
+
+    /// The node crashed, the frames its tasks still send fail.
+    pub fn shut_down(&self) {
+        self.down.store(true, Ordering::Relaxed)
+    }
+
+    /// Counts a frame received by the node.
+    pub fn received(&self, frame: &Frame) {
+        Traffic::count(&mut self.traffic.lock().unwrap().received, frame)
+    }
+
+    /// The traffic counted since the last call.
+    pub fn take_traffic(&self) -> Traffic {
+        std::mem::take(&mut *self.traffic.lock().unwrap())
+    }
+
+    /// Emulates the given links to the peers, cut during the windows counted from `start`,
+    /// the other peers are reached directly. The frames delayed on the previous links are still
+    /// delivered.
+    pub fn set_links(&self, start: Instant, links: Vec<(String, Link, Vec<Cut>)>) {
+        let ms = |ms: u32| start + std::time::Duration::from_millis(ms as u64);
+        let mut emulated = self.links.lock().unwrap();
+        emulated.clear();
+        for (addr, link, cuts) in links {
+            if link.is_perfect() && cuts.is_empty() {
+                continue;
+            }
+            let delayed = self.delay(addr.clone());
+            let link = EmulatedLink {
+                link,
+                busy_until: start,
+                cuts: cuts
+                    .into_iter()
+                    .map(|(at, heal)| (ms(at), heal.map(ms)))
+                    .collect(),
+                delayed,
+            };
+            emulated.insert(addr, link);
+        }
+    }
+
+    fn route(&self, addr: &str, len: usize) -> Route {
+        let mut links = self.links.lock().unwrap();
+        let Some(emulated) = links.get_mut(addr) else {
+            return Route::Direct;
+        };
+        let mut rng = rng();
+        if emulated.link.drops(&mut rng) {
+            return Route::Lost;
+        }
+        let ms = |ms: f64| std::time::Duration::from_secs_f64(ms / 1000.0);
+        let start = emulated.busy_until.max(Instant::now());
+        emulated.busy_until = start + ms(emulated.link.transmission(len));
+        let mut at = emulated.busy_until + ms(emulated.link.sample_latency(&mut rng));
+        // A frame reaching a cut link waits for the heal, as retransmitted over TCP.
+        while let Some((_, heal)) = emulated
+            .cuts
+            .iter()
+            .find(|(cut, heal)| *cut <= at && heal.is_none_or(|heal| at < heal))
+        {
+            match heal {
+                Some(heal) => at = *heal,
+                None => return Route::Lost,
+            }
+        }
+        Route::Delayed(emulated.delayed.clone(), at)
+    }
+
+    /// Spawns the task sending the frames of an emulated link at their delivery time.
+    fn delay(&self, addr: String) -> Sender<(Instant, Frame)> {
+        let (sender, mut receiver) = channel::<(Instant, Frame)>(INBOX_SIZE);
+        let (inner, failed) = (self.inner.clone(), self.failed.clone());
+        tokio::spawn(async move {
+            while let Some((at, frame)) = receiver.recv().await {
+                tokio::time::sleep_until(at).await;
+                if failed.lock().unwrap().contains(&addr) {
+                    continue;
+                }
+                if let Err(e) = inner.send(&addr, frame).await {
+                    eprintln!("Failed to send to {addr}: {e}");
+                    failed.lock().unwrap().insert(addr.clone());
+                }
+            }
+        });
+        sender
+    }
+}
+
+#[async_trait]
+impl Transport for EmulatedTransport {
+    /// Sends `frame` through the emulated link to `addr` when there is one, the failures of
+    /// the delayed frames are only reported. A peer the frame cannot reach is marked failed.
+    async fn send(&self, addr: &str, frame: Frame) -> std::io::Result<()> {
+        if self.down.load(Ordering::Relaxed) {
+            return Err(not_connected(addr));
+        }
+        if self.has_failed(addr) {
+            return Err(crashed(addr));
+        }
+        if addr != self.interface {
+            Traffic::count(&mut self.traffic.lock().unwrap().sent, &frame);
+        }
+        match self.route(addr, frame.encoded_len()) {
+            Route::Direct => {
+                let sent = self.inner.send(addr, frame).await;
+                if sent.is_err() {
+                    self.mark_failed(addr);
+                }
+                sent
+            },
+            Route::Lost => Ok(()),
+            Route::Delayed(delayed, at) => delayed
+                .send((at, frame))
+                .await
+                .map_err(|_| not_connected(addr)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(i: u8) -> Frame {
+        Frame::new(CommandCode::PROTOCOL.into(), &[i; 3])
+    }
+
+    #[tokio::test]
+    async fn memory_frames_reach_the_registered_inbox() {
+        let network = MemoryNetwork::default();
+        let mut inbox = network.register("memory:1");
+        for i in 0..10 {
+            network.send("memory:1", frame(i)).await.unwrap();
+        }
+        for i in 0..10 {
+            assert_eq!(inbox.recv().await, Some(frame(i)));
+        }
+        assert!(network.send("memory:2", frame(0)).await.is_err());
+        drop(inbox);
+        assert!(network.send("memory:1", frame(0)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn emulated_links_delay_drop_and_cut_frames() {
+        let network = Arc::new(MemoryNetwork::default());
+        let mut inboxes =
+            ["delayed", "partitioned", "lossy", "cut"].map(|addr| network.register(addr));
+        let link = Link {
+            latency: 200,
+            ..Link::default()
+        };
+        let lost = Link {
+            loss: 100,
+            ..Link::default()
+        };
+        let links = vec![
+            ("delayed".to_string(), link, Vec::new()),
+            (
+                "partitioned".to_string(),
+                Link::default(),
+                vec![(0, Some(300))],
+            ),
+            ("lossy".to_string(), lost, Vec::new()),
+            ("cut".to_string(), Link::default(), vec![(0, None)]),
+        ];
+        let transport = EmulatedTransport::new(network.clone(), String::new());
+        let timer = Instant::now();
+        transport.set_links(timer, links);
+        for i in 0..10 {
+            for addr in ["delayed", "partitioned", "lossy", "cut"] {
+                transport.send(addr, frame(i)).await.unwrap();
+            }
+        }
+        for i in 0..10 {
+            assert_eq!(inboxes[0].recv().await, Some(frame(i)));
+        }
+        assert!(timer.elapsed().as_millis() >= 200);
+        for i in 0..10 {
+            assert_eq!(inboxes[1].recv().await, Some(frame(i)));
+        }
+        assert!(timer.elapsed().as_millis() >= 300);
+        // The frames are dropped, not delayed.
+        assert!(inboxes[2].try_recv().is_err());
+        assert!(inboxes[3].try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn traffic_is_counted_by_code() {
+        let network = Arc::new(MemoryNetwork::default());
+        let _inboxes = ["peer", "interface"].map(|addr| network.register(addr));
+        let transport = EmulatedTransport::new(network, "interface".to_string());
+        for i in 0..3 {
+            transport.send("peer", frame(i)).await.unwrap();
+        }
+        transport.send("interface", frame(0)).await.unwrap();
+        let key = Frame::new(CommandCode::KEY.into(), &[0; 10]);
+        transport.received(&key);
+        let mut traffic = Traffic::default();
+        traffic.sent[CommandCode::PROTOCOL as usize] = (3, 3 * frame(0).encoded_len() as u64);
+        traffic.received[CommandCode::KEY as usize] = (1, key.encoded_len() as u64);
+        assert_eq!(transport.take_traffic(), traffic);
+        assert_eq!(transport.take_traffic(), Traffic::default());
+    }
+
+    #[tokio::test]
+    async fn crashed_peers_are_tracked() {
+        let network = Arc::new(MemoryNetwork::default());
+        let mut inbox = network.register("peer");
+        let transport = EmulatedTransport::new(network.clone(), "interface".to_string());
+        // A peer that cannot be reached is marked, the interface is not.
+        assert!(transport.send("crashed", frame(0)).await.is_err());
+        assert!(transport.send("interface", frame(0)).await.is_err());
+        assert!(transport.has_failed("crashed") && !transport.has_failed("interface"));
+        let _inbox = network.register("crashed");
+        let failed = transport.send("crashed", frame(1)).await.unwrap_err();
+        assert_eq!(failed.kind(), std::io::ErrorKind::ConnectionAborted);
+        transport.mark_failed("peer");
+        assert!(transport.send("peer", frame(1)).await.is_err());
+        transport.clear_failures();
+        transport.send("crashed", frame(2)).await.unwrap();
+        transport.send("peer", frame(2)).await.unwrap();
+        assert_eq!(inbox.recv().await, Some(frame(2)));
+        network.unregister("peer");
+        assert!(network.send("peer", frame(3)).await.is_err());
+        transport.shut_down();
+        assert!(transport.send("crashed", frame(3)).await.is_err());
+    }
+}